@@ -4,64 +4,13 @@ use chess::util::*;
 use std::io;
 use chess::Move::Castle;
 
-fn cord_to_square(cord: &str) -> Square {
-    let mut chars = cord.chars();
-    let file = match chars.next().unwrap() {
-        'a' => File::A,
-        'b' => File::B,
-        'c' => File::C,
-        'd' => File::D,
-        'e' => File::E,
-        'f' => File::F,
-        'g' => File::G,
-        'h' => File::H,
-        _ => {
-            panic!("Invalid file")
-        },
-    };
-    let rank = match chars.next().unwrap() {
-        '1' => Rank::R1,
-        '2' => Rank::R2,
-        '3' => Rank::R3,
-        '4' => Rank::R4,
-        '5' => Rank::R5,
-        '6' => Rank::R6,
-        '7' => Rank::R7,
-        '8' => Rank::R8,
-        _ => {
-            panic!("Invalid rank")
-        },
-    };
-    Square {
-        file,
-        rank,
-    }
-}
-
-fn option_to_char(o: Option<Move>) -> char {
-    match o {
-        Some(_) => '#',
-        None => 'O',
-    }
-}
-
 fn main() {
     let mut game = Game::new();
 
     let mut input = String::new();
     let stdin = io::stdin();
 
-    println!("  A  B  C  D  E  F  G  H ");
-    let mut y_cord = 8;
-    for y in RANK_ARRAY.iter().rev() {
-        let mut t = String::new();
-
-        for x in FILE_ARRAY {
-            t += format!("[{}]", piece_to_char(game.board[y.clone()][x])).as_str();
-        }
-        println!("{y_cord}{}",t);
-        y_cord -= 1;
-    }
+    print!("{}", game.board);
 
     loop {
         //displaying the game state
@@ -71,16 +20,13 @@ fn main() {
             println!("{:?} is in check!", game.turn);
         }
 
-        let castling_color = match game.turn {
-            Color::Black => game.castling.black,
-            Color::White => game.castling.white,
-        };
+        let (king_side, queen_side) = game.castling.rights(game.turn);
         print!("These are the remaining castles for {:?}: ", game.turn);
-        if let Some(side) = castling_color.0 {
-            print!("{:?}, ", side);
+        if let Some(rook) = king_side {
+            print!("king side with the {:?} rook, ", rook);
         }
-        if let Some(side) = castling_color.1 {
-            print!("{:?}", side);
+        if let Some(rook) = queen_side {
+            print!("queen side with the {:?} rook", rook);
         }
         println!();
 
@@ -94,32 +40,29 @@ fn main() {
         println!("Which piece do you want to move? (e.g. e2)");
 
         input.clear();
-        stdin.read_line(&mut input).unwrap();
-        let cords = input.as_str();
+        if stdin.read_line(&mut input).unwrap() == 0 {
+            return;
+        }
 
-        let from = cord_to_square(cords);
+        let from: Square = match input.trim().parse() {
+            Ok(square) => square,
+            Err(e) => {
+                println!("{}", e);
+                continue;
+            },
+        };
 
-        match game.possible_moves(from, true) {
-            Ok((moves, castels)) => {
-                // prints possible board
+        match game.possible_moves(from) {
+            Ok((moves, castles)) => {
+                // prints the board with the possible moves highlighted
                 println!("Possible moves: ");
-                println!("  A  B  C  D  E  F  G  H ");
-                let mut y_cord = 8;
-                for y in RANK_ARRAY.iter().rev() {
-                    let mut t = String::new();
-
-                    for x in FILE_ARRAY {
-                        t += format!("[{}]", option_to_char(moves[y.clone()][x]).to_string()).as_str();
-                    }
-                    println!("{y_cord}{}",t);
-                    y_cord -= 1;
-                }
-                if castels.len() > 0 {
+                print!("{}", game.board.display().highlight(&moves));
+                if !castles.is_empty() {
                     print!("Avalable castles: ");
-                    for castle in castels {
-                        print!("{:?}", match castle {
-                            Castle { side: CastlingSide::KingSide } => "king side (e.g O-O)",
-                            Castle { side: CastlingSide::QueenSide } => "queen side (e.g O-O-O)",
+                    for castle in castles {
+                        print!("{} ", match castle {
+                            Castle { side: CastlingSide::KingSide, .. } => "king side (e.g O-O)",
+                            Castle { side: CastlingSide::QueenSide, .. } => "queen side (e.g O-O-O)",
                             _ => "error",
                         });
                     }
@@ -135,41 +78,64 @@ fn main() {
         println!("Where do you want to move it? (e.g. e4, O-O or O-O-O)");
 
         input.clear();
-        stdin.read_line(&mut input).unwrap();
-        let cords = input.as_str();
+        if stdin.read_line(&mut input).unwrap() == 0 {
+            return;
+        }
 
-        let mv = match cords {
-            "O-O" =>  { Move::Castle { side: CastlingSide::KingSide } },
-            "O-O-O" => { Move::Castle { side: CastlingSide::QueenSide } },
-            _ => { Move::Normal { from, to: cord_to_square(cords) } },
+        let mv = match input.trim() {
+            "O-O" => Move::Castle { side: CastlingSide::KingSide, color: game.turn },
+            "O-O-O" => Move::Castle { side: CastlingSide::QueenSide, color: game.turn },
+            cords => match cords.parse() {
+                Ok(to) => Move::Normal { from, to },
+                Err(e) => {
+                    println!("{}", e);
+                    continue;
+                },
+            },
         };
 
         match mv {
             Move::Normal { from, to } => {
-                println!("{:?} is trying to move {}, from {:?}, {:?} to {:?}, {:?}", game.turn, piece_to_char(game.board[from]), from.file, from.rank, to.file, to.rank);
+                println!("{:?} is trying to move {}, from {} to {}", game.turn, game.board[from].map_or(String::from("nothing"), |piece| piece.to_string()), from, to);
             },
-            Move::Castle { side } => {
+            Move::Castle { side, .. } => {
                 println!("{:?} is castling {}", game.turn, match side {
                     CastlingSide::KingSide => "king side",
                     CastlingSide::QueenSide => "queen side",
                 });
             },
+            Move::Promotion { .. } => unreachable!(),
         }
 
         //make move
         match game.try_move(mv) {
             Ok(()) => {
-                // prints board if successful
-                println!("  A  B  C  D  E  F  G  H ");
-                let mut y_cord = 8;
-                for y in RANK_ARRAY.iter().rev() {
-                    let mut t = String::new();
+                while game.game_status == GameStatus::Promoting {
+                    println!("Which piece do you want to promote to? (q, r, b or n)");
 
-                    for x in FILE_ARRAY {
-                        t += format!("[{}]", piece_to_char(game.board[y.clone()][x])).as_str();
+                    input.clear();
+                    if stdin.read_line(&mut input).unwrap() == 0 {
+                        return;
+                    }
+
+                    let piece = match input.trim() {
+                        "q" => PieceTypes::Queen,
+                        "r" => PieceTypes::Rook,
+                        "b" => PieceTypes::Bishop,
+                        "n" => PieceTypes::Knight,
+                        _ => continue,
+                    };
+                    if let Err(e) = game.promote(piece) {
+                        println!("{}", e);
                     }
-                    println!("{y_cord}{}",t);
-                    y_cord -= 1;
+                }
+
+                // prints board if successful
+                print!("{}", game.board);
+
+                if let Some(result) = game.result() {
+                    println!("The game is over: {:?}", result);
+                    break;
                 }
             },
             Err(e) => {
@@ -180,15 +146,3 @@ fn main() {
         }
     }
 }
-
-fn piece_to_char(piece: Option<Piece>) -> char {
-    match piece {
-        Some(Piece { piece: PieceTypes::Bishop, .. }) => 'b',
-        Some(Piece { piece: PieceTypes::Rook, .. }) => 'r',
-        Some(Piece { piece: PieceTypes::King, .. }) => 'K',
-        Some(Piece { piece: PieceTypes::Queen, .. }) => 'q',
-        Some(Piece { piece: PieceTypes::Knight, .. }) => 'k',
-        Some(Piece { piece: PieceTypes::Pawn(_), .. }) => 'p',
-        None => '.',
-    }
-}
\ No newline at end of file
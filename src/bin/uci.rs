@@ -0,0 +1,8 @@
+use std::io;
+
+use chess::uci::UciEngine;
+
+fn main() {
+    let mut engine = UciEngine::new(io::stdout());
+    engine.run(io::stdin().lock());
+}
@@ -0,0 +1,194 @@
+use crate::{Color, PieceTypes};
+use crate::util::{Square, FILE_ARRAY, RANK_ARRAY};
+
+///one bit per square, the bit of a square is rank * 8 + file
+pub(crate) type Bitboard = u64;
+
+const KNIGHT_OFFSETS: [(i32, i32); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_OFFSETS: [(i32, i32); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const WHITE_PAWN_OFFSETS: [(i32, i32); 2] = [(-1, 1), (1, 1)];
+const BLACK_PAWN_OFFSETS: [(i32, i32); 2] = [(-1, -1), (1, -1)];
+
+///the ray directions. The first four go towards higher bits and the last four towards lower bits.
+const DIRECTIONS: [(i32, i32); 8] = [(0, 1), (1, 0), (1, 1), (-1, 1), (0, -1), (-1, 0), (-1, -1), (1, -1)];
+const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 4, 5];
+const BISHOP_DIRECTIONS: [usize; 4] = [2, 3, 6, 7];
+
+pub(crate) const KNIGHT_ATTACKS: [Bitboard; 64] = step_attacks(&KNIGHT_OFFSETS);
+pub(crate) const KING_ATTACKS: [Bitboard; 64] = step_attacks(&KING_OFFSETS);
+///the squares a pawn attacks, indexed by the color of the pawn and then its square
+pub(crate) const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [step_attacks(&WHITE_PAWN_OFFSETS), step_attacks(&BLACK_PAWN_OFFSETS)];
+const RAYS: [[Bitboard; 64]; 8] = rays();
+
+const fn step_attacks(offsets: &[(i32, i32)]) -> [Bitboard; 64] {
+    let mut attacks = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut i = 0;
+        while i < offsets.len() {
+            let file = (square % 8) as i32 + offsets[i].0;
+            let rank = (square / 8) as i32 + offsets[i].1;
+            if file >= 0 && file < 8 && rank >= 0 && rank < 8 {
+                attacks[square] |= 1 << (rank * 8 + file);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    attacks
+}
+
+const fn rays() -> [[Bitboard; 64]; 8] {
+    let mut rays = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut square = 0;
+        while square < 64 {
+            let mut file = (square % 8) as i32 + DIRECTIONS[direction].0;
+            let mut rank = (square / 8) as i32 + DIRECTIONS[direction].1;
+            while file >= 0 && file < 8 && rank >= 0 && rank < 8 {
+                rays[direction][square] |= 1 << (rank * 8 + file);
+                file += DIRECTIONS[direction].0;
+                rank += DIRECTIONS[direction].1;
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    rays
+}
+
+///the squares attacked along a ray up to and including the first occupied square
+fn ray_attacks(direction: usize, square: usize, occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[direction][square];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+
+    let blocker = if direction < 4 { blockers.trailing_zeros() } else { 63 - blockers.leading_zeros() };
+    ray ^ RAYS[direction][blocker as usize]
+}
+
+pub(crate) fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    ROOK_DIRECTIONS.iter().fold(0, |attacks, direction| attacks | ray_attacks(*direction, square, occupied))
+}
+
+pub(crate) fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    BISHOP_DIRECTIONS.iter().fold(0, |attacks, direction| attacks | ray_attacks(*direction, square, occupied))
+}
+
+pub(crate) fn index(square: Square) -> usize {
+    square.rank as usize * 8 + square.file as usize
+}
+
+pub(crate) fn square(index: usize) -> Square {
+    Square { file: FILE_ARRAY[index % 8], rank: RANK_ARRAY[index / 8] }
+}
+
+pub(crate) fn bit(square: Square) -> Bitboard {
+    1 << index(square)
+}
+
+///the squares of all set bits from the lowest to the highest
+pub(crate) fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = Square> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let index = bitboard.trailing_zeros() as usize;
+        bitboard &= bitboard - 1;
+        Some(square(index))
+    })
+}
+
+pub(crate) fn piece_index(piece: PieceTypes) -> usize {
+    match piece {
+        PieceTypes::Pawn(_) => 0,
+        PieceTypes::Bishop => 1,
+        PieceTypes::Knight => 2,
+        PieceTypes::Rook => 3,
+        PieceTypes::Queen => 4,
+        PieceTypes::King => 5,
+    }
+}
+
+pub(crate) fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{File, Rank};
+
+    #[test]
+    fn attack_tables() {
+        let a1 = index(Square { file: File::A, rank: Rank::R1 });
+        let e4 = index(Square { file: File::E, rank: Rank::R4 });
+        assert_eq!(KNIGHT_ATTACKS[a1].count_ones(), 2);
+        assert_eq!(KNIGHT_ATTACKS[e4].count_ones(), 8);
+        assert_eq!(KING_ATTACKS[a1].count_ones(), 3);
+        assert_eq!(KING_ATTACKS[e4].count_ones(), 8);
+        assert_eq!(PAWN_ATTACKS[0][e4], bit(Square { file: File::D, rank: Rank::R5 }) | bit(Square { file: File::F, rank: Rank::R5 }));
+        assert_eq!(PAWN_ATTACKS[1][a1], 0);
+    }
+
+    #[test]
+    fn sliding_attacks() {
+        let e4 = index(Square { file: File::E, rank: Rank::R4 });
+        assert_eq!(rook_attacks(e4, 0).count_ones(), 14);
+        assert_eq!(bishop_attacks(e4, 0).count_ones(), 13);
+
+        //blockers on e6 and c2 are included but the squares behind them are not
+        let e6 = bit(Square { file: File::E, rank: Rank::R6 });
+        let c2 = bit(Square { file: File::C, rank: Rank::R2 });
+        let rook = rook_attacks(e4, e6 | c2);
+        assert!(rook & e6 != 0);
+        assert!(rook & bit(Square { file: File::E, rank: Rank::R7 }) == 0);
+        let bishop = bishop_attacks(e4, e6 | c2);
+        assert!(bishop & c2 != 0);
+        assert!(bishop & bit(Square { file: File::B, rank: Rank::R1 }) == 0);
+        assert_eq!(squares(rook_attacks(e4, !0)).count(), 4);
+    }
+
+    #[test]
+    fn board_written_through_indexing() {
+        use crate::{Color, Game, Piece, PieceTypes};
+
+        let e4 = Square { file: File::E, rank: Rank::R4 };
+        let e2 = Square { file: File::E, rank: Rank::R2 };
+        let knight = Some(Piece { piece: PieceTypes::Knight, color: Color::White });
+        let mut indexed = Game::new();
+        let mut set = Game::new();
+
+        indexed.board[e4] = knight;
+        indexed.board[Rank::R2][File::E] = None;
+        set.board.set(e4, knight);
+        set.board.set(e2, None);
+        assert_eq!(indexed.board.pieces(PieceTypes::Knight, Color::White), set.board.pieces(PieceTypes::Knight, Color::White));
+        assert_eq!(indexed.hash(), set.hash());
+        assert_eq!(indexed.legal_moves(), set.legal_moves());
+
+        //set on a written board starts from the written squares
+        indexed.board.set(e2, knight);
+        set.board.set(e2, knight);
+        assert_eq!(indexed.board.occupied(), set.board.occupied());
+        assert_eq!(indexed.hash(), set.hash());
+
+        //writing another rank folds the first one into the bitboards
+        let d8 = Square { file: File::D, rank: Rank::R8 };
+        indexed.board[d8] = None;
+        indexed.board.rows_mut().squares[0].squares[1] = None;
+        set.board.set(d8, None);
+        set.board.set(Square { file: File::B, rank: Rank::R1 }, None);
+        assert_eq!(indexed.board.rows().squares[0].squares[1], None);
+        assert_eq!(indexed.board.pieces(PieceTypes::Queen, Color::Black), 0);
+        assert_eq!(indexed.board.occupied(), set.board.occupied());
+        assert_eq!(indexed.hash(), set.hash());
+        assert_eq!(indexed.legal_moves(), set.legal_moves());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::Game;
+
+///the ways to place two knights on the five squares left after the bishops and queen are placed, in the order of the Scharnagl numbering
+const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+///puts piece on the empty square number index of the rank
+fn place(rank: &mut [char; 8], index: usize, piece: char) {
+    let file = (0..8).filter(|f| rank[*f] == ' ').nth(index).unwrap();
+    rank[file] = piece;
+}
+
+/**
+The white back rank of Chess960 start position id as FEN letters, using the Scharnagl numbering where 518 is the standard position.
+The bishops are placed first, then the queen and the knights on the empty squares and the rooks and the king on the three squares left.
+*/
+fn back_rank(id: u16) -> [char; 8] {
+    let mut rank = [' '; 8];
+    let mut n = id as usize;
+
+    rank[n % 4 * 2 + 1] = 'B';
+    n /= 4;
+    rank[n % 4 * 2] = 'B';
+    n /= 4;
+
+    place(&mut rank, n % 6, 'Q');
+    n /= 6;
+
+    //the second knight is placed after the first so its index among the empty squares is one lower
+    let (first, second) = KNIGHTS[n];
+    place(&mut rank, first, 'N');
+    place(&mut rank, second - 1, 'N');
+
+    for piece in ['R', 'K', 'R'] {
+        place(&mut rank, 0, piece);
+    }
+
+    rank
+}
+
+impl Game {
+    /**
+    Creates a Chess960 game from the start position with number id between 0 and 959 in the Scharnagl numbering, where 518 is the standard position.
+    The king is always between the rooks and the castles end with the king and rook on the same squares as in standard chess.
+    Panics if id is 960 or higher.
+    */
+    pub fn new_chess960(id: u16) -> Game {
+        assert!(id < 960, "there are only 960 start positions");
+
+        let white: String = back_rank(id).iter().collect();
+        let black = white.to_lowercase();
+        let fen = format!("{black}/pppppppp/8/8/8/8/PPPPPPPP/{white} w KQkq - 0 1");
+
+        let mut game = Game::from_fen(&fen).expect("every Chess960 start position is a valid FEN");
+        game.chess960 = true;
+        game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CastlingSide, Color, Move};
+    use crate::util::File;
+
+    #[test]
+    fn start_positions() {
+        assert_eq!(Game::new_chess960(518).to_fen(), crate::fen::STARTING_FEN);
+        assert_eq!(Game::new_chess960(0).to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(Game::new_chess960(959).to_fen(), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1");
+
+        let positions: std::collections::HashSet<String> = (0..960).map(|id| back_rank(id).iter().collect()).collect();
+        assert_eq!(positions.len(), 960);
+        assert!(Game::new_chess960(0).chess960);
+    }
+
+    #[test]
+    fn castling_rights_follow_the_rooks() {
+        let game = Game::new_chess960(0);
+        assert_eq!(game.castling.white, (Some(File::H), Some(File::F)));
+
+        //the rook on b1 is not the outermost rook on the queen side so it is written with its file
+        let fen = "1r2k1r1/8/8/8/8/8/8/RR2K2R w KBkq - 0 1";
+        let game = Game::from_fen(fen).unwrap();
+        assert_eq!(game.castling.white, (Some(File::H), Some(File::B)));
+        assert_eq!(game.castling.black, (Some(File::G), Some(File::B)));
+        assert!(game.chess960);
+        assert_eq!(game.to_fen(), fen);
+        assert_eq!(Game::from_fen("1r2k1r1/8/8/8/8/8/8/RR2K2R w HBgb - 0 1").unwrap().to_fen(), fen);
+    }
+
+    #[test]
+    fn castling_where_king_or_rook_stays() {
+        //the king on g1 only moves the rook and the rook on d8 stays while the king moves to c8
+        let mut game = Game::from_fen("3rk3/8/8/8/8/8/8/R5KR w Hd - 0 1").unwrap();
+        let castle = Move::Castle { side: CastlingSide::KingSide, color: Color::White };
+        assert_eq!(game.to_uci(castle), "g1h1");
+        game.try_move(castle).unwrap();
+        assert_eq!(game.to_fen(), "3rk3/8/8/8/8/8/8/R4RK1 b q - 1 1");
+
+        let castle = game.parse_uci("e8d8").unwrap();
+        assert_eq!(castle, Move::Castle { side: CastlingSide::QueenSide, color: Color::Black });
+        game.try_move(castle).unwrap();
+        assert_eq!(game.to_fen(), "2kr4/8/8/8/8/8/8/R4RK1 w - - 2 2");
+
+        game.undo();
+        game.undo();
+        assert_eq!(game.to_fen(), "3rk3/8/8/8/8/8/8/R5KR w Kq - 0 1");
+    }
+
+    #[test]
+    fn castling_needs_free_and_safe_squares() {
+        //the king on b1 has to pass c1 to f1 which are free but f1 is attacked
+        let game = Game::from_fen("k4r2/8/8/8/8/8/8/1K5R w H - 0 1").unwrap();
+        assert!(!game.legal_moves().contains(&Move::Castle { side: CastlingSide::KingSide, color: Color::White }));
+
+        //the rook on a1 has to pass b1 where the knight stands even though the king does not
+        let game = Game::from_fen("7k/8/8/8/8/8/8/RNK5 w A - 0 1").unwrap();
+        assert!(!game.legal_moves().contains(&Move::Castle { side: CastlingSide::QueenSide, color: Color::White }));
+        let game = Game::from_fen("7k/8/8/8/8/8/8/R1K5 w A - 0 1").unwrap();
+        assert!(game.legal_moves().contains(&Move::Castle { side: CastlingSide::QueenSide, color: Color::White }));
+    }
+
+    #[test]
+    fn perft() {
+        let game = Game::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        assert_eq!((1..=3).map(|depth| game.perft(depth)).collect::<Vec<_>>(), [21, 528, 12189]);
+    }
+}
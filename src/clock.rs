@@ -0,0 +1,439 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{Color, DrawReason, Game, GameStatus, PieceTypes};
+use crate::bitboard;
+
+///Where the clock gets the time from. The time only has to increase, where it starts does not matter.
+pub trait TimeSource: Send + Sync {
+    fn now(&self) -> Duration;
+}
+
+///The real time, measured from when the source was created.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemTime {
+    start: Instant,
+}
+
+impl SystemTime {
+    pub fn new() -> SystemTime {
+        SystemTime { start: Instant::now() }
+    }
+}
+
+impl Default for SystemTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for SystemTime {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+///A time that only moves when advance is called. Clones share the same time, which makes clocks deterministic in tests.
+#[derive(Clone, Debug, Default)]
+pub struct ManualTime {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualTime {
+    pub fn new() -> ManualTime {
+        ManualTime::default()
+    }
+
+    pub fn advance(&self, time: Duration) {
+        if let Ok(mut now) = self.now.lock() {
+            *now += time;
+        }
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        self.now.lock().map(|now| *now).unwrap_or_default()
+    }
+}
+
+///What a player gets for every move.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Bonus {
+    None,
+    ///the increment is added after every move
+    Fischer(Duration),
+    ///the time used for the move is given back after the move, but at most the delay
+    Bronstein(Duration),
+    ///the clock waits for the delay before it starts counting down
+    Delay(Duration),
+}
+
+///A period of the game. The time of the stage is added when the previous stage is completed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stage {
+    pub time: Duration,
+    ///the number of moves of the stage or None for the rest of the game
+    pub moves: Option<u32>,
+    pub bonus: Bonus,
+}
+
+///The time control of a game as a list of stages. The last stage should last for the rest of the game.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeControl {
+    pub stages: Vec<Stage>,
+}
+
+impl TimeControl {
+    pub fn sudden_death(time: Duration) -> TimeControl {
+        TimeControl { stages: vec![Stage { time, moves: None, bonus: Bonus::None }] }
+    }
+
+    pub fn fischer(time: Duration, increment: Duration) -> TimeControl {
+        TimeControl { stages: vec![Stage { time, moves: None, bonus: Bonus::Fischer(increment) }] }
+    }
+
+    pub fn bronstein(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl { stages: vec![Stage { time, moves: None, bonus: Bonus::Bronstein(delay) }] }
+    }
+
+    pub fn simple_delay(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl { stages: vec![Stage { time, moves: None, bonus: Bonus::Delay(delay) }] }
+    }
+
+    ///a control with several stages, for example 40 moves in 90 minutes and then 30 minutes for the rest of the game, both with 30 seconds increment
+    pub fn stages(stages: Vec<Stage>) -> TimeControl {
+        TimeControl { stages }
+    }
+}
+
+/**
+A chess clock for both players. Only one side runs at a time, pressing the clock ends the move of the running side and starts the other side.
+The time is read from a TimeSource, which is the real time unless another source is given.
+*/
+#[derive(Clone)]
+pub struct Clock {
+    control: TimeControl,
+    time_source: Arc<dyn TimeSource>,
+    ///the time left for white and black, not counting the move in progress
+    remaining: [Duration; 2],
+    stage: [usize; 2],
+    ///moves made in the current stage by white and black
+    stage_moves: [u32; 2],
+    ///the side that is running and the time its move started
+    running: Option<(Color, Duration)>,
+    flagged: Option<Color>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Clock {
+        Clock::with_time_source(control, SystemTime::new())
+    }
+
+    pub fn with_time_source(control: TimeControl, time_source: impl TimeSource + 'static) -> Clock {
+        let time = control.stages.first().map_or(Duration::ZERO, |stage| stage.time);
+        Clock {
+            control,
+            time_source: Arc::new(time_source),
+            remaining: [time; 2],
+            stage: [0; 2],
+            stage_moves: [0; 2],
+            running: None,
+            flagged: None,
+        }
+    }
+
+    ///starts the time of color, stopping the other side without ending its move
+    pub fn start(&mut self, color: Color) {
+        if self.flagged.is_none() {
+            self.running = Some((color, self.time_source.now()));
+        }
+    }
+
+    ///stops both sides, the time used by the running side is subtracted
+    pub fn stop(&mut self) {
+        if let Some((color, _)) = self.running {
+            let index = bitboard::color_index(color);
+            self.remaining[index] = self.remaining(color);
+            self.running = None;
+        }
+    }
+
+    ///the side whose time is running
+    pub fn running(&self) -> Option<Color> {
+        self.running.map(|(color, _)| color)
+    }
+
+    ///the time left for color including the move in progress
+    pub fn remaining(&self, color: Color) -> Duration {
+        let index = bitboard::color_index(color);
+        match self.running {
+            Some((running, start)) if running == color => self.remaining[index].saturating_sub(self.used(color, self.time_source.now() - start)),
+            _ => self.remaining[index],
+        }
+    }
+
+    ///the side that has run out of time
+    pub fn flagged(&self) -> Option<Color> {
+        self.flagged.or_else(|| self.running().filter(|color| self.remaining(*color).is_zero()))
+    }
+
+    /**
+    Ends the move of the running side and starts the time of the other side. The bonus of the stage is given and the next stage is started
+    when the moves of the current stage are done. If the running side had already run out of time the clock stops instead.
+    */
+    pub fn press(&mut self) {
+        let Some((color, start)) = self.running else { return };
+        let index = bitboard::color_index(color);
+        let now = self.time_source.now();
+        let elapsed = now - start;
+
+        if self.remaining(color).is_zero() {
+            self.remaining[index] = Duration::ZERO;
+            self.flagged = Some(color);
+            self.running = None;
+            return;
+        }
+
+        let stage = self.control.stages[self.stage[index]];
+        self.remaining[index] -= self.used(color, elapsed);
+        self.remaining[index] += match stage.bonus {
+            Bonus::Fischer(increment) => increment,
+            Bonus::Bronstein(delay) => elapsed.min(delay),
+            _ => Duration::ZERO,
+        };
+
+        self.stage_moves[index] += 1;
+        if stage.moves.is_some_and(|moves| self.stage_moves[index] >= moves) && self.stage[index] + 1 < self.control.stages.len() {
+            self.stage[index] += 1;
+            self.stage_moves[index] = 0;
+            self.remaining[index] += self.control.stages[self.stage[index]].time;
+        }
+
+        self.running = Some((color.opposite(), now));
+    }
+
+    ///the time that counts against color when elapsed has passed since its move started
+    fn used(&self, color: Color, elapsed: Duration) -> Duration {
+        match self.control.stages[self.stage[bitboard::color_index(color)]].bonus {
+            Bonus::Delay(delay) => elapsed.saturating_sub(delay),
+            _ => elapsed,
+        }
+    }
+}
+
+///The state of a clock without its time source, which is how a clock is written with serde.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct ClockState {
+    control: TimeControl,
+    ///the time left for white and black, counting the move in progress
+    remaining: [Duration; 2],
+    stage: [usize; 2],
+    stage_moves: [u32; 2],
+    running: Option<Color>,
+    flagged: Option<Color>,
+}
+
+#[cfg(feature = "serde")]
+impl Clock {
+    pub(crate) fn state(&self) -> ClockState {
+        ClockState {
+            control: self.control.clone(),
+            remaining: [self.remaining(Color::White), self.remaining(Color::Black)],
+            stage: self.stage,
+            stage_moves: self.stage_moves,
+            running: self.running(),
+            flagged: self.flagged,
+        }
+    }
+
+    ///a clock on the real time with the times of state. The running side continues from the time it had left. None if a stage does not exist.
+    pub(crate) fn from_state(state: ClockState) -> Option<Clock> {
+        if state.stage.iter().any(|stage| *stage >= state.control.stages.len()) {
+            return None;
+        }
+
+        let mut clock = Clock::new(state.control);
+        clock.remaining = state.remaining;
+        clock.stage = state.stage;
+        clock.stage_moves = state.stage_moves;
+        clock.flagged = state.flagged;
+        if let Some(color) = state.running {
+            clock.start(color);
+        }
+        Some(clock)
+    }
+}
+
+impl Game {
+    ///puts the game on a clock and starts the time of the player in turn
+    pub fn set_clock(&mut self, mut clock: Clock) {
+        clock.start(self.turn);
+        self.clock = Some(clock);
+    }
+
+    /**
+    Checks if the player in turn has run out of time and ends the game if so. The opponent wins with GameStatus::Timeout
+    unless the opponent can not checkmate with the material left, then the game is a draw. Returns true if the game is over on time.
+    */
+    pub fn check_time(&mut self) -> bool {
+        if matches!(self.game_status, GameStatus::Timeout(_) | GameStatus::Draw(DrawReason::TimeoutVsInsufficientMaterial)) {
+            return true;
+        }
+        if self.result().is_some() {
+            return false;
+        }
+
+        let Some(loser) = self.clock.as_ref().and_then(|clock| clock.flagged()) else {
+            return false;
+        };
+        if let Some(clock) = self.clock.as_mut() {
+            clock.stop();
+        }
+
+        let winner = loser.opposite();
+        self.game_status = match self.can_checkmate(winner) {
+            true => GameStatus::Timeout(winner),
+            false => GameStatus::Draw(DrawReason::TimeoutVsInsufficientMaterial),
+        };
+        true
+    }
+
+    ///checks if color has enough material to checkmate with any series of legal moves
+    fn can_checkmate(&self, color: Color) -> bool {
+        let count = |piece, color| self.board.pieces(piece, color).count_ones();
+        if count(PieceTypes::Pawn(true), color) + count(PieceTypes::Rook, color) + count(PieceTypes::Queen, color) > 0 {
+            return true;
+        }
+
+        let knights = count(PieceTypes::Knight, color);
+        let bishops = self.board.pieces(PieceTypes::Bishop, color);
+        const LIGHT_SQUARES: u64 = 0x55aa55aa55aa55aa;
+        let bishop_colors = (bishops & LIGHT_SQUARES != 0) as u32 + (bishops & !LIGHT_SQUARES != 0) as u32;
+
+        match (knights, bishop_colors) {
+            (0, 0) => false,
+            //a lone minor piece can only mate when the opponent has pieces that can block its king
+            (1, 0) | (0, 1) => self.board.color(color.opposite()).count_ones() > 1,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Move, MoveError};
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    fn start(control: TimeControl) -> (Clock, ManualTime) {
+        let time = ManualTime::new();
+        let mut clock = Clock::with_time_source(control, time.clone());
+        clock.start(Color::White);
+        (clock, time)
+    }
+
+    #[test]
+    fn sudden_death_and_fischer() {
+        let (mut clock, time) = start(TimeControl::sudden_death(secs(60)));
+        time.advance(secs(10));
+        assert_eq!(clock.remaining(Color::White), secs(50));
+        clock.press();
+        time.advance(secs(5));
+        assert_eq!((clock.remaining(Color::White), clock.remaining(Color::Black)), (secs(50), secs(55)));
+
+        let (mut clock, time) = start(TimeControl::fischer(secs(60), secs(2)));
+        time.advance(secs(10));
+        clock.press();
+        assert_eq!(clock.remaining(Color::White), secs(52));
+        assert_eq!(clock.running(), Some(Color::Black));
+    }
+
+    #[test]
+    fn bronstein_and_simple_delay() {
+        let (mut clock, time) = start(TimeControl::bronstein(secs(60), secs(5)));
+        time.advance(secs(3));
+        assert_eq!(clock.remaining(Color::White), secs(57));
+        clock.press();
+        assert_eq!(clock.remaining(Color::White), secs(60));
+        time.advance(secs(8));
+        clock.press();
+        assert_eq!(clock.remaining(Color::Black), secs(57));
+
+        let (mut clock, time) = start(TimeControl::simple_delay(secs(60), secs(5)));
+        time.advance(secs(3));
+        assert_eq!(clock.remaining(Color::White), secs(60));
+        time.advance(secs(5));
+        assert_eq!(clock.remaining(Color::White), secs(57));
+        clock.press();
+        assert_eq!(clock.remaining(Color::White), secs(57));
+    }
+
+    #[test]
+    fn stages() {
+        //2 moves in 10 seconds, then 20 seconds with 1 second increment
+        let (mut clock, time) = start(TimeControl::stages(vec![
+            Stage { time: secs(10), moves: Some(2), bonus: Bonus::None },
+            Stage { time: secs(20), moves: None, bonus: Bonus::Fischer(secs(1)) },
+        ]));
+        for _ in 0..2 {
+            time.advance(secs(2));
+            clock.press();
+            clock.press();
+        }
+        assert_eq!(clock.remaining(Color::White), secs(26));
+        time.advance(secs(2));
+        clock.press();
+        assert_eq!(clock.remaining(Color::White), secs(25));
+    }
+
+    #[test]
+    fn flag_fall_ends_the_game() {
+        let time = ManualTime::new();
+        let mut game = Game::new();
+        game.set_clock(Clock::with_time_source(TimeControl::fischer(secs(60), secs(1)), time.clone()));
+
+        let mv = game.parse_san("e4").unwrap();
+        time.advance(secs(30));
+        game.try_move(mv).unwrap();
+        assert_eq!(game.clock.as_ref().unwrap().remaining(Color::White), secs(31));
+
+        time.advance(secs(61));
+        assert!(game.check_time());
+        assert_eq!(game.game_status, GameStatus::Timeout(Color::White));
+        let mv = Move::Normal { from: "e7".parse().unwrap(), to: "e5".parse().unwrap() };
+        assert_eq!(game.try_move(mv), Err(MoveError::Timeout));
+    }
+
+    #[test]
+    fn flag_fall_against_insufficient_material() {
+        let time = ManualTime::new();
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K1N1 b - - 0 1").unwrap();
+        game.set_clock(Clock::with_time_source(TimeControl::sudden_death(secs(1)), time.clone()));
+        time.advance(secs(2));
+        assert!(game.check_time());
+        assert_eq!(game.game_status, GameStatus::Timeout(Color::White));
+
+        let mut game = Game::from_fen("4k2n/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        game.set_clock(Clock::with_time_source(TimeControl::sudden_death(secs(1)), time.clone()));
+        time.advance(secs(2));
+        let mv = Move::Normal { from: "e2".parse().unwrap(), to: "e4".parse().unwrap() };
+        assert_eq!(game.try_move(mv), Err(MoveError::Timeout));
+        //a knight can mate a king that is blocked by its own pawn
+        assert_eq!(game.game_status, GameStatus::Timeout(Color::Black));
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        game.set_clock(Clock::with_time_source(TimeControl::sudden_death(secs(1)), time.clone()));
+        time.advance(secs(2));
+        assert!(game.check_time());
+        assert_eq!(game.game_status, GameStatus::Draw(DrawReason::TimeoutVsInsufficientMaterial));
+    }
+
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+use crate::{Color, Game, Piece, PieceTypes};
+use crate::fen::piece_to_fen;
+use crate::util::{Board, BoardMove, Square, FILE_ARRAY, RANK_ARRAY};
+
+///How the pieces are drawn.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PieceStyle {
+    ///the FEN letters, uppercase for white and lowercase for black
+    #[default]
+    Ascii,
+    ///the chess figurines of Unicode, outlined for white and filled for black
+    Unicode,
+}
+
+/**
+A board ready to be printed. Every square is drawn as [x] where x is the piece or . for an empty square, and squares highlighted with
+a BoardMove are drawn as (x). Created with Board::display and configured with the builder methods:
+```ignore
+println!("{}", game.board.display().unicode().flipped(true).highlight(&moves));
+```
+*/
+#[derive(Clone, Copy)]
+pub struct BoardDisplay<'a> {
+    board: &'a Board,
+    style: PieceStyle,
+    flipped: bool,
+    coordinates: bool,
+    highlight: Option<&'a BoardMove>,
+}
+
+impl<'a> BoardDisplay<'a> {
+    pub fn style(mut self, style: PieceStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn ascii(self) -> Self {
+        self.style(PieceStyle::Ascii)
+    }
+
+    pub fn unicode(self) -> Self {
+        self.style(PieceStyle::Unicode)
+    }
+
+    ///draws the board from the view of black with rank 1 at the top and the h file to the left
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    ///draws the rank numbers to the left and the file letters below the board, on by default
+    pub fn coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    ///highlights the squares that have a move, e.g. the targets returned by possible_moves
+    pub fn highlight(mut self, moves: &'a BoardMove) -> Self {
+        self.highlight = Some(moves);
+        self
+    }
+}
+
+impl Board {
+    ///the board as ASCII FEN letters from the view of white with coordinates, see BoardDisplay for the other options
+    pub fn display(&self) -> BoardDisplay<'_> {
+        BoardDisplay {
+            board: self,
+            style: PieceStyle::Ascii,
+            flipped: false,
+            coordinates: true,
+            highlight: None,
+        }
+    }
+}
+
+fn piece_to_unicode(piece: Piece) -> char {
+    match (piece.color, piece.piece) {
+        (Color::White, PieceTypes::King) => '♔',
+        (Color::White, PieceTypes::Queen) => '♕',
+        (Color::White, PieceTypes::Rook) => '♖',
+        (Color::White, PieceTypes::Bishop) => '♗',
+        (Color::White, PieceTypes::Knight) => '♘',
+        (Color::White, PieceTypes::Pawn(_)) => '♙',
+        (Color::Black, PieceTypes::King) => '♚',
+        (Color::Black, PieceTypes::Queen) => '♛',
+        (Color::Black, PieceTypes::Rook) => '♜',
+        (Color::Black, PieceTypes::Bishop) => '♝',
+        (Color::Black, PieceTypes::Knight) => '♞',
+        (Color::Black, PieceTypes::Pawn(_)) => '♟',
+    }
+}
+
+impl fmt::Display for BoardDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut ranks = RANK_ARRAY;
+        let mut files = FILE_ARRAY;
+        if self.flipped {
+            files.reverse();
+        }
+        else {
+            ranks.reverse();
+        }
+
+        for rank in ranks {
+            if self.coordinates {
+                write!(f, "{}", rank.num() + 1)?;
+            }
+            for file in files {
+                let square = Square { file, rank };
+                let piece = match (self.board[square], self.style) {
+                    (Some(piece), PieceStyle::Ascii) => piece_to_fen(piece),
+                    (Some(piece), PieceStyle::Unicode) => piece_to_unicode(piece),
+                    (None, _) => '.',
+                };
+                match self.highlight.is_some_and(|moves| moves[square].is_some()) {
+                    true => write!(f, "({})", piece)?,
+                    false => write!(f, "[{}]", piece)?,
+                }
+            }
+            writeln!(f)?;
+        }
+
+        if self.coordinates {
+            for file in files {
+                write!(f, "  {}", (b'a' + file.num() as u8) as char)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.display().fmt(f)
+    }
+}
+
+///Prints the board from the view of white followed by the FEN of the position.
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.board, self.to_fen())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{File, Rank};
+
+    #[test]
+    fn ascii_and_unicode() {
+        let game = Game::new();
+        assert_eq!(game.board.to_string(), "\
+8[r][n][b][q][k][b][n][r]
+7[p][p][p][p][p][p][p][p]
+6[.][.][.][.][.][.][.][.]
+5[.][.][.][.][.][.][.][.]
+4[.][.][.][.][.][.][.][.]
+3[.][.][.][.][.][.][.][.]
+2[P][P][P][P][P][P][P][P]
+1[R][N][B][Q][K][B][N][R]
+  a  b  c  d  e  f  g  h
+");
+        assert!(game.to_string().ends_with("  a  b  c  d  e  f  g  h\nrnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"));
+
+        let unicode = game.board.display().unicode().coordinates(false).to_string();
+        assert_eq!(unicode.lines().next(), Some("[♜][♞][♝][♛][♚][♝][♞][♜]"));
+        assert_eq!(unicode.lines().last(), Some("[♖][♘][♗][♕][♔][♗][♘][♖]"));
+    }
+
+    #[test]
+    fn flipped_with_highlight() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let (moves, _) = game.possible_moves(Square { file: File::A, rank: Rank::R1 }).unwrap();
+        let text = game.board.display().flipped(true).highlight(&moves).to_string();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], "1[.][.][.][K](.)(.)(.)[R]");
+        assert_eq!(lines[1], "2[.][.][.][.][.][.][.](.)");
+        assert_eq!(lines[7], "8[.][.][.][k][.][.][.](.)");
+        assert_eq!(lines[8], "  h  g  f  e  d  c  b  a");
+    }
+}
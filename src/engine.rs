@@ -0,0 +1,399 @@
+use std::error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::Duration;
+
+use crate::{Game, Move};
+use crate::uci::UciError;
+
+///The limits sent with the go command. Fields that are None are left out, with every field None the engine decides itself.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GoLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+}
+
+impl GoLimits {
+    pub fn depth(depth: u32) -> GoLimits {
+        GoLimits { depth: Some(depth), ..GoLimits::default() }
+    }
+
+    pub fn movetime(movetime: Duration) -> GoLimits {
+        GoLimits { movetime: Some(movetime), ..GoLimits::default() }
+    }
+
+    fn command(&self) -> String {
+        let mut command = String::from("go");
+        let mut push = |name: &str, value: Option<u128>| {
+            if let Some(value) = value {
+                command += &format!(" {} {}", name, value);
+            }
+        };
+        push("depth", self.depth.map(u128::from));
+        push("nodes", self.nodes.map(u128::from));
+        push("movetime", self.movetime.map(|d| d.as_millis()));
+        push("wtime", self.wtime.map(|d| d.as_millis()));
+        push("btime", self.btime.map(|d| d.as_millis()));
+        push("winc", self.winc.map(|d| d.as_millis()));
+        push("binc", self.binc.map(|d| d.as_millis()));
+        push("movestogo", self.movestogo.map(u128::from));
+        command
+    }
+}
+
+///A score reported by the engine from the view of the side to move.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Score {
+    Centipawns(i32),
+    ///mate in this many moves, negative if the side to move gets mated
+    Mate(i32),
+}
+
+///An info line from the engine. Fields the engine did not send are None.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Info {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<Duration>,
+    ///the principal variation starting from the searched position. It ends early if the engine sends a move that is not legal.
+    pub pv: Vec<Move>,
+    pub string: Option<String>,
+}
+
+///The result of a search by the engine.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BestMove {
+    ///None if the engine answered "bestmove 0000" or "bestmove (none)", which it does when there are no legal moves
+    pub best_move: Option<Move>,
+    pub ponder: Option<Move>,
+    ///every info line sent during the search in order
+    pub info: Vec<Info>,
+}
+
+impl BestMove {
+    ///the last info line with a score, usually from the deepest iteration
+    pub fn score(&self) -> Option<Score> {
+        self.info.iter().rev().find_map(|info| info.score)
+    }
+}
+
+/**
+An external UCI engine running as a child process. The uci handshake is done when the engine is started and the process is
+asked to quit when the Engine is dropped.
+*/
+pub struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    pub name: Option<String>,
+    pub author: Option<String>,
+    ///the options the engine listed in the handshake as the option lines without the leading "option"
+    pub options: Vec<String>,
+}
+
+impl Engine {
+    ///starts the engine executable at path
+    pub fn new(path: impl AsRef<OsStr>) -> Result<Engine, EngineError> {
+        Engine::from_command(Command::new(path))
+    }
+
+    ///starts the engine with a prepared command, for example to pass arguments or set the working directory
+    pub fn from_command(mut command: Command) -> Result<Engine, EngineError> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or(EngineError::Closed)?;
+        let stdout = BufReader::new(child.stdout.take().ok_or(EngineError::Closed)?);
+
+        let mut engine = Engine { child, stdin, stdout, name: None, author: None, options: vec![] };
+
+        engine.send("uci")?;
+        loop {
+            let line = engine.read_line()?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = Some(name.to_string());
+            }
+            else if let Some(author) = line.strip_prefix("id author ") {
+                engine.author = Some(author.to_string());
+            }
+            else if let Some(option) = line.strip_prefix("option ") {
+                engine.options.push(option.to_string());
+            }
+            else if line == "uciok" {
+                break;
+            }
+        }
+        engine.is_ready()?;
+
+        Ok(engine)
+    }
+
+    ///sends isready and waits for readyok
+    pub fn is_ready(&mut self) -> Result<(), EngineError> {
+        self.send("isready")?;
+        while self.read_line()? != "readyok" {}
+        Ok(())
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), EngineError> {
+        self.send(&format!("setoption name {} value {}", name, value))?;
+        self.is_ready()
+    }
+
+    ///tells the engine that the next position is from a new game
+    pub fn new_game(&mut self) -> Result<(), EngineError> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    ///sends the position of the game as the FEN it started from followed by the moves played
+    pub fn set_position(&mut self, game: &Game) -> Result<(), EngineError> {
+        let mut command = format!("position fen {}", game.initial_fen);
+        let mut replay = Game::from_fen(&game.initial_fen).map_err(|_| EngineError::InvalidPosition)?;
+
+        if !game.moves.is_empty() {
+            command += " moves";
+        }
+        for mv in &game.moves {
+            command += " ";
+            command += &replay.to_uci(*mv);
+            replay.try_move(*mv).map_err(|_| EngineError::InvalidPosition)?;
+        }
+
+        self.send(&command)
+    }
+
+    ///sends the position and searches it, blocking until the engine answers with bestmove
+    pub fn go(&mut self, game: &Game, limits: GoLimits) -> Result<BestMove, EngineError> {
+        self.go_with_info(game, limits, |_| {})
+    }
+
+    ///like go but info is called for every info line as it arrives
+    pub fn go_with_info(&mut self, game: &Game, limits: GoLimits, mut info: impl FnMut(&Info)) -> Result<BestMove, EngineError> {
+        self.set_position(game)?;
+        self.send(&limits.command())?;
+
+        let mut lines = vec![];
+        loop {
+            let line = self.read_line()?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => {
+                    let parsed = parse_info(game, &line);
+                    info(&parsed);
+                    lines.push(parsed);
+                },
+                Some("bestmove") => {
+                    let best_move = match tokens.next() {
+                        Some("0000") | Some("(none)") | None => None,
+                        Some(uci) => Some(game.parse_uci(uci).map_err(|e| EngineError::InvalidMove(uci.to_string(), e))?),
+                    };
+                    let ponder = match (best_move, tokens.next(), tokens.next()) {
+                        (Some(mv), Some("ponder"), Some(uci)) => {
+                            let mut after = game.clone();
+                            after.try_move(mv).ok().and_then(|_| after.parse_uci(uci).ok())
+                        },
+                        _ => None,
+                    };
+                    return Ok(BestMove { best_move, ponder, info: lines });
+                },
+                _ => {},
+            }
+        }
+    }
+
+    ///sends quit and waits for the process to exit
+    pub fn quit(mut self) -> Result<(), EngineError> {
+        self.send("quit")?;
+        self.child.wait()?;
+        Ok(())
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), EngineError> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> Result<String, EngineError> {
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            return Err(EngineError::Closed);
+        }
+        Ok(line.trim().to_string())
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = writeln!(self.stdin, "quit");
+            let _ = self.stdin.flush();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+///parses an info line, the pv moves are parsed against the game
+fn parse_info(game: &Game, line: &str) -> Info {
+    let mut info = Info::default();
+    let mut tokens = line.split_whitespace().skip(1).peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => info.depth = tokens.next().and_then(|t| t.parse().ok()),
+            "seldepth" => info.seldepth = tokens.next().and_then(|t| t.parse().ok()),
+            "multipv" => info.multipv = tokens.next().and_then(|t| t.parse().ok()),
+            "nodes" => info.nodes = tokens.next().and_then(|t| t.parse().ok()),
+            "nps" => info.nps = tokens.next().and_then(|t| t.parse().ok()),
+            "time" => info.time = tokens.next().and_then(|t| t.parse().ok()).map(Duration::from_millis),
+            "score" => {
+                info.score = match (tokens.next(), tokens.next().and_then(|t| t.parse().ok())) {
+                    (Some("cp"), Some(value)) => Some(Score::Centipawns(value)),
+                    (Some("mate"), Some(value)) => Some(Score::Mate(value)),
+                    _ => None,
+                };
+            },
+            "pv" => {
+                let mut position = game.clone();
+                while let Some(uci) = tokens.next_if(|t| t.len() >= 4 && t.as_bytes()[1].is_ascii_digit()) {
+                    match position.parse_uci(uci) {
+                        Ok(mv) if position.try_move(mv).is_ok() => info.pv.push(mv),
+                        _ => break,
+                    }
+                }
+            },
+            "string" => {
+                info.string = Some(tokens.by_ref().collect::<Vec<_>>().join(" "));
+            },
+            _ => {},
+        }
+    }
+
+    info
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EngineError {
+    Io(io::ErrorKind),
+    Closed,
+    InvalidPosition,
+    InvalidMove(String, UciError),
+}
+
+impl From<io::Error> for EngineError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::BrokenPipe => EngineError::Closed,
+            kind => EngineError::Io(kind),
+        }
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineError::Io(kind) => write!(f, "Io: could not communicate with the engine process: {}", kind),
+            EngineError::Closed => write!(f, "Closed: the engine process closed its output"),
+            EngineError::InvalidPosition => write!(f, "Invalid position: the moves of the game could not be replayed from its initial FEN"),
+            EngineError::InvalidMove(uci, error) => write!(f, "Invalid move: the engine sent {}. {}", uci, error),
+        }
+    }
+}
+
+impl error::Error for EngineError { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sq;
+    use crate::util::{File, Rank};
+
+    ///a stand in engine that repeats the position it got in an info string and always answers with the same search
+    const SCRIPT: &str = r#"
+        while read cmd args; do
+            case "$cmd" in
+                uci) echo "id name Scripted 1.0"; echo "id author Tests"; echo "option name Hash type spin default 16 min 1 max 64"; echo "uciok";;
+                isready) echo "readyok";;
+                position) position="$args";;
+                go)
+                    echo "info string $position"
+                    echo "info string $args"
+                    echo "info depth 1 score cp 20 nodes 20 pv g1f3"
+                    echo "info depth 2 seldepth 4 multipv 1 score mate -3 nodes 400 nps 4000 time 100 pv g1f3 b8c6 e2e9"
+                    echo "bestmove g1f3 ponder b8c6";;
+                quit) exit 0;;
+            esac
+        done
+    "#;
+
+    fn scripted() -> Engine {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(SCRIPT);
+        Engine::from_command(command).unwrap()
+    }
+
+    #[test]
+    fn handshake() {
+        let engine = scripted();
+        assert_eq!(engine.name.as_deref(), Some("Scripted 1.0"));
+        assert_eq!(engine.author.as_deref(), Some("Tests"));
+        assert_eq!(engine.options, vec!["name Hash type spin default 16 min 1 max 64"]);
+        engine.quit().unwrap();
+    }
+
+    #[test]
+    fn go_parses_info_and_bestmove() {
+        let mut engine = scripted();
+        let mut game = Game::new();
+        game.try_move(game.parse_uci("e2e4").unwrap()).unwrap();
+        game.try_move(game.parse_uci("e7e5").unwrap()).unwrap();
+
+        let mut count = 0;
+        let result = engine.go_with_info(&game, GoLimits { depth: Some(2), movetime: Some(Duration::from_millis(500)), ..GoLimits::default() }, |_| count += 1).unwrap();
+        assert_eq!(count, 4);
+
+        assert_eq!(result.info[0].string.as_deref(), Some(format!("fen {} moves e2e4 e7e5", crate::fen::STARTING_FEN).as_str()));
+        assert_eq!(result.info[1].string.as_deref(), Some("depth 2 movetime 500"));
+        assert_eq!(result.best_move, Some(Move::Normal { from: sq(File::G, Rank::R1), to: sq(File::F, Rank::R3) }));
+        assert_eq!(result.ponder, Some(Move::Normal { from: sq(File::B, Rank::R8), to: sq(File::C, Rank::R6) }));
+        assert_eq!(result.score(), Some(Score::Mate(-3)));
+
+        //the pv stops at the illegal move
+        let info = &result.info[3];
+        assert_eq!(info.pv.len(), 2);
+        assert_eq!((info.depth, info.seldepth, info.multipv, info.nodes, info.nps), (Some(2), Some(4), Some(1), Some(400), Some(4000)));
+        assert_eq!(info.time, Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn errors() {
+        //the engine sends a move that is not legal in the position
+        let mut engine = scripted();
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(matches!(engine.go(&game, GoLimits::depth(1)), Err(EngineError::InvalidMove(_, UciError::IllegalMove))));
+
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("exit 0");
+        assert_eq!(Engine::from_command(command).err(), Some(EngineError::Closed));
+        assert!(matches!(Engine::new("/nonexistent/engine").err(), Some(EngineError::Io(_))));
+    }
+}
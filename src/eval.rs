@@ -0,0 +1,403 @@
+use crate::{Color, Game, PieceTypes};
+use crate::bitboard::{self, Bitboard, KING_ATTACKS, KNIGHT_ATTACKS, PAWN_ATTACKS, bishop_attacks, rook_attacks};
+
+/**
+Scores a position in centipawns from the view of the player in turn. Positive means the player in turn is better.
+The search only calls evaluate in quiet positions, checkmate, stalemate and draws are handled by the search.
+Any Fn(&Game) -> i32 is an evaluator as well.
+*/
+pub trait Evaluator {
+    fn evaluate(&self, game: &Game) -> i32;
+}
+
+impl<F: Fn(&Game) -> i32> Evaluator for F {
+    fn evaluate(&self, game: &Game) -> i32 {
+        self(game)
+    }
+}
+
+/**
+The evaluator used by the search unless another one is given. It scores material, piece-square tables, mobility, pawn structure and king safety
+separately for the middlegame and the endgame and blends the two by how much material is left on the board.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DefaultEvaluator;
+
+impl Evaluator for DefaultEvaluator {
+    fn evaluate(&self, game: &Game) -> i32 {
+        let (white, black) = (side_score(game, Color::White), side_score(game, Color::Black));
+        let mg = white.0 - black.0;
+        let eg = white.1 - black.1;
+
+        let phase = phase(game);
+        let score = (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE;
+
+        match game.turn {
+            Color::White => score,
+            Color::Black => -score,
+        }
+    }
+}
+
+///the value of a piece in centipawns
+pub fn piece_value(piece: PieceTypes) -> i32 {
+    match piece {
+        PieceTypes::Pawn(_) => 100,
+        PieceTypes::Knight => 320,
+        PieceTypes::Bishop => 330,
+        PieceTypes::Rook => 500,
+        PieceTypes::Queen => 900,
+        PieceTypes::King => 0,
+    }
+}
+
+///a middlegame and an endgame score
+type Score = (i32, i32);
+
+const PIECES: [PieceTypes; 6] = [PieceTypes::Pawn(true), PieceTypes::Knight, PieceTypes::Bishop, PieceTypes::Rook, PieceTypes::Queen, PieceTypes::King];
+const MAX_PHASE: i32 = 24;
+const BISHOP_PAIR: Score = (30, 50);
+const DOUBLED_PAWN: Score = (-10, -20);
+const ISOLATED_PAWN: Score = (-10, -15);
+///bonus for a passed pawn by how many ranks it has advanced
+const PASSED_PAWN: [Score; 8] = [(0, 0), (5, 10), (10, 20), (15, 35), (25, 55), (40, 80), (60, 110), (0, 0)];
+const PAWN_SHIELD: i32 = 12;
+const KING_ATTACK: i32 = 6;
+const FILE_A: Bitboard = 0x0101010101010101;
+
+fn material(piece: PieceTypes) -> Score {
+    match piece {
+        PieceTypes::Pawn(_) => (100, 120),
+        PieceTypes::Knight => (320, 300),
+        PieceTypes::Bishop => (330, 320),
+        PieceTypes::Rook => (500, 530),
+        PieceTypes::Queen => (900, 950),
+        PieceTypes::King => (0, 0),
+    }
+}
+
+fn mobility_weight(piece: PieceTypes) -> Score {
+    match piece {
+        PieceTypes::Knight => (4, 4),
+        PieceTypes::Bishop => (5, 5),
+        PieceTypes::Rook => (2, 4),
+        PieceTypes::Queen => (1, 2),
+        _ => (0, 0),
+    }
+}
+
+fn phase_weight(piece: PieceTypes) -> i32 {
+    match piece {
+        PieceTypes::Knight | PieceTypes::Bishop => 1,
+        PieceTypes::Rook => 2,
+        PieceTypes::Queen => 4,
+        _ => 0,
+    }
+}
+
+///24 with all pieces on the board down to 0 with only kings and pawns
+fn phase(game: &Game) -> i32 {
+    let phase: i32 = PIECES.iter()
+        .map(|piece| phase_weight(*piece) * (game.board.pieces(*piece, Color::White) | game.board.pieces(*piece, Color::Black)).count_ones() as i32)
+        .sum();
+    phase.min(MAX_PHASE)
+}
+
+///the index into the piece-square tables, which are written from the view of white with the eighth rank first
+fn table_index(square: usize, color: Color) -> usize {
+    match color {
+        Color::White => (7 - square / 8) * 8 + square % 8,
+        Color::Black => square,
+    }
+}
+
+fn piece_square(piece: PieceTypes, square: usize, color: Color) -> Score {
+    let i = table_index(square, color);
+    match piece {
+        PieceTypes::Pawn(_) => (PAWN_TABLE[i], PAWN_ENDGAME_TABLE[i]),
+        PieceTypes::Knight => (KNIGHT_TABLE[i], KNIGHT_TABLE[i]),
+        PieceTypes::Bishop => (BISHOP_TABLE[i], BISHOP_TABLE[i]),
+        PieceTypes::Rook => (ROOK_TABLE[i], ROOK_TABLE[i]),
+        PieceTypes::Queen => (QUEEN_TABLE[i], QUEEN_TABLE[i]),
+        PieceTypes::King => (KING_TABLE[i], KING_ENDGAME_TABLE[i]),
+    }
+}
+
+fn add(score: &mut Score, (mg, eg): Score, times: i32) {
+    score.0 += mg * times;
+    score.1 += eg * times;
+}
+
+///everything that is scored for one color
+fn side_score(game: &Game, color: Color) -> Score {
+    let board = &game.board;
+    let occupied = board.occupied();
+    let own = board.color(color);
+    let enemy_pawns = board.pieces(PieceTypes::Pawn(true), color.opposite());
+    let enemy_pawn_attacks = bitboard::squares(enemy_pawns)
+        .fold(0, |attacks, s| attacks | PAWN_ATTACKS[bitboard::color_index(color.opposite())][bitboard::index(s)]);
+    let enemy_king_zone = bitboard::squares(board.pieces(PieceTypes::King, color.opposite()))
+        .fold(0, |zone, s| zone | KING_ATTACKS[bitboard::index(s)] | bitboard::bit(s));
+
+    let mut score = (0, 0);
+    let mut king_attackers = 0;
+    let mut king_attacks = 0;
+
+    for piece in PIECES {
+        for square in bitboard::squares(board.pieces(piece, color)) {
+            let index = bitboard::index(square);
+            add(&mut score, material(piece), 1);
+            add(&mut score, piece_square(piece, index, color), 1);
+
+            let attacks = match piece {
+                PieceTypes::Knight => KNIGHT_ATTACKS[index],
+                PieceTypes::Bishop => bishop_attacks(index, occupied),
+                PieceTypes::Rook => rook_attacks(index, occupied),
+                PieceTypes::Queen => bishop_attacks(index, occupied) | rook_attacks(index, occupied),
+                _ => continue,
+            };
+
+            add(&mut score, mobility_weight(piece), (attacks & !own & !enemy_pawn_attacks).count_ones() as i32);
+            if attacks & enemy_king_zone != 0 {
+                king_attackers += 1;
+                king_attacks += (attacks & enemy_king_zone).count_ones() as i32;
+            }
+        }
+    }
+
+    if board.pieces(PieceTypes::Bishop, color).count_ones() >= 2 {
+        add(&mut score, BISHOP_PAIR, 1);
+    }
+
+    //a single attacker is rarely dangerous
+    if king_attackers >= 2 {
+        score.0 += king_attacks * KING_ATTACK * king_attackers.min(4) / 2;
+    }
+
+    add(&mut score, pawn_structure(game, color), 1);
+    score.0 += pawn_shield(game, color);
+
+    score
+}
+
+fn pawn_structure(game: &Game, color: Color) -> Score {
+    let pawns = game.board.pieces(PieceTypes::Pawn(true), color);
+    let enemy_pawns = game.board.pieces(PieceTypes::Pawn(true), color.opposite());
+    let mut score = (0, 0);
+
+    for file in 0..8 {
+        let count = (pawns & (FILE_A << file)).count_ones() as i32;
+        if count > 1 {
+            add(&mut score, DOUBLED_PAWN, count - 1);
+        }
+    }
+
+    for square in bitboard::squares(pawns) {
+        let index = bitboard::index(square);
+        let (file, rank) = (index % 8, index / 8);
+        let adjacent = adjacent_files(file);
+
+        if pawns & adjacent == 0 {
+            add(&mut score, ISOLATED_PAWN, 1);
+        }
+
+        let ahead = match color {
+            Color::White if rank < 7 => !0u64 << (8 * (rank + 1)),
+            Color::Black => (1u64 << (8 * rank)) - 1,
+            _ => 0,
+        };
+        if enemy_pawns & ahead & (adjacent | FILE_A << file) == 0 {
+            let advanced = match color {
+                Color::White => rank,
+                Color::Black => 7 - rank,
+            };
+            add(&mut score, PASSED_PAWN[advanced], 1);
+        }
+    }
+
+    score
+}
+
+///own pawns on the two ranks in front of the king on its own and the adjacent files
+fn pawn_shield(game: &Game, color: Color) -> i32 {
+    let Some(king) = bitboard::squares(game.board.pieces(PieceTypes::King, color)).next() else {
+        return 0;
+    };
+    let index = bitboard::index(king);
+    let (file, rank) = (index % 8, index / 8);
+
+    let ranks: Bitboard = match color {
+        Color::White if rank < 6 => 0xffff << (8 * (rank + 1)),
+        Color::Black if rank > 1 => 0xffff << (8 * (rank - 2)),
+        _ => return 0,
+    };
+    let files = adjacent_files(file) | FILE_A << file;
+    (game.board.pieces(PieceTypes::Pawn(true), color) & ranks & files).count_ones() as i32 * PAWN_SHIELD
+}
+
+fn adjacent_files(file: usize) -> Bitboard {
+    let mut files = 0;
+    if file > 0 {
+        files |= FILE_A << (file - 1);
+    }
+    if file < 7 {
+        files |= FILE_A << (file + 1);
+    }
+    files
+}
+
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const PAWN_ENDGAME_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    80, 80, 80, 80, 80, 80, 80, 80,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    30, 30, 30, 30, 30, 30, 30, 30,
+    20, 20, 20, 20, 20, 20, 20, 20,
+    10, 10, 10, 10, 10, 10, 10, 10,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(fen: &str) -> i32 {
+        DefaultEvaluator.evaluate(&Game::from_fen(fen).unwrap())
+    }
+
+    ///the same position with the colors swapped and the board flipped
+    fn mirror(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let swap_case = |s: &str| s.chars().map(|c| if c.is_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }).collect::<String>();
+        let board = fields[0].split('/').rev().map(swap_case).collect::<Vec<_>>().join("/");
+        let turn = if fields[1] == "w" { "b" } else { "w" };
+        let mut castling: Vec<char> = swap_case(fields[2]).chars().collect();
+        castling.sort_by_key(|c| (c.is_lowercase(), *c != 'K' && *c != 'k'));
+        let en_passant = fields[3].replace('6', "x").replace('3', "6").replace('x', "3");
+        format!("{} {} {} {} {} {}", board, turn, castling.into_iter().collect::<String>(), en_passant, fields[4], fields[5])
+    }
+
+    #[test]
+    fn symmetric_positions() {
+        assert_eq!(evaluate(crate::fen::STARTING_FEN), 0);
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        ] {
+            assert_eq!(evaluate(fen), evaluate(&mirror(fen)), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn positional_terms() {
+        //extra material
+        assert!(evaluate("4k3/8/8/8/8/8/8/R3K3 w - - 0 1") > 400);
+        assert!(evaluate("4k3/8/8/8/8/8/8/R3K3 b - - 0 1") < -400);
+        //a centralized knight
+        assert!(evaluate("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1") > evaluate("4k3/8/8/8/8/8/8/N3K3 w - - 0 1"));
+        //a passed pawn against a blocked one
+        assert!(evaluate("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1") > evaluate("4k3/8/3p4/3P4/8/8/8/4K3 w - - 0 1") + 100);
+        //doubled and isolated pawns
+        assert!(evaluate("4k3/8/8/8/8/8/1PP5/4K3 w - - 0 1") > evaluate("4k3/8/8/8/8/1P6/1P6/4K3 w - - 0 1"));
+        //a king behind its pawns in the middlegame
+        let sheltered = "rnbq1rk1/ppppbppp/4pn2/8/8/4PN2/PPPPBPPP/RNBQ1RK1 w - - 0 1";
+        let exposed = "rnbq1rk1/ppppbppp/4pn2/8/8/4PN2/PPPPBPPP/RNBQ1R1K w - - 0 1";
+        assert!(evaluate(sheltered) >= evaluate(exposed));
+        //the king goes to the center in the endgame
+        assert!(evaluate("7k/8/8/8/3K4/8/8/8 w - - 0 1") > evaluate("7k/8/8/8/8/8/8/K7 w - - 0 1"));
+    }
+
+    #[test]
+    fn closures_are_evaluators() {
+        let material = |game: &Game| game.captured.len() as i32;
+        assert_eq!(material.evaluate(&Game::new()), 0);
+    }
+}
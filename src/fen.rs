@@ -0,0 +1,412 @@
+use std::error;
+use std::fmt;
+use crate::{Castling, CastlingSide, Color, Game, GameStatus, Piece, PieceTypes};
+use crate::util::{Board, File, Rank, Square, FILE_ARRAY, RANK_ARRAY};
+
+///The FEN of the standard starting position.
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+impl Game {
+    /**
+    Creates a game from a FEN string. The halfmove clock and fullmove number can be left out in which case they default to 0 and 1.
+    Captured pieces are not part of a FEN so the captured field of the returned game is empty.
+    The castling field can be written in X-FEN for Chess960 positions, the game is then marked as a Chess960 game.
+    */
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        let mut fields = fen.split_whitespace();
+
+        let board = parse_board(fields.next().ok_or(FenError::MissingField)?)?;
+
+        let turn = match fields.next().ok_or(FenError::MissingField)? {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(FenError::InvalidTurn),
+        };
+
+        let castling = parse_castling(fields.next().ok_or(FenError::MissingField)?, &board)?;
+
+        let en_passant = match fields.next().ok_or(FenError::MissingField)? {
+            "-" => None,
+            s => {
+                let square: Square = s.parse().map_err(|_| FenError::InvalidEnPassant)?;
+                let valid_row = match turn {
+                    Color::White => Rank::R6,
+                    Color::Black => Rank::R3,
+                };
+                if square.rank != valid_row {
+                    return Err(FenError::InvalidEnPassant);
+                }
+                Some(square)
+            },
+        };
+
+        let halfmove_clock = match fields.next() {
+            Some(s) => s.parse().map_err(|_| FenError::InvalidClock)?,
+            None => 0,
+        };
+        let fullmove_number = match fields.next() {
+            Some(s) => s.parse().map_err(|_| FenError::InvalidClock)?,
+            None => 1,
+        };
+        if fullmove_number == 0 {
+            return Err(FenError::InvalidClock);
+        }
+
+        if fields.next().is_some() {
+            return Err(FenError::TooManyFields);
+        }
+
+        let chess960 = chess960_castling(&castling, &board);
+        let mut game = Game {
+            board,
+            turn,
+            captured: vec![],
+            castling,
+            halfmove_clock,
+            fullmove_number,
+            en_passant,
+            check: false,
+            game_status: GameStatus::Ongoing,
+            moves: vec![],
+            initial_fen: String::new(),
+            positions: vec![],
+            history: vec![],
+            redo_moves: vec![],
+            clock: None,
+            chess960,
+            draw_offer: None,
+        };
+        game.initial_fen = game.to_fen();
+
+        if game.in_check(turn.opposite()) {
+            return Err(FenError::InvalidBoard);
+        }
+
+        game.update_status();
+
+        Ok(game)
+    }
+
+    ///Returns the FEN string of the current position.
+    pub fn to_fen(&self) -> String {
+        let mut fen = board_to_string(&self.board);
+
+        fen.push(' ');
+        fen.push(match self.turn {
+            Color::White => 'w',
+            Color::Black => 'b',
+        });
+
+        fen.push(' ');
+        fen.push_str(&castling_to_string(&self.castling, &self.board));
+
+        fen.push(' ');
+        match self.en_passant {
+            Some(square) => fen.push_str(&square.to_string()),
+            None => fen.push('-'),
+        }
+
+        fen.push_str(&format!(" {} {}", self.halfmove_clock, self.fullmove_number));
+
+        fen
+    }
+}
+
+///the piece placement field of a FEN, the ranks from 8 to 1 separated by /
+pub(crate) fn board_to_string(board: &Board) -> String {
+    let mut fen = String::new();
+
+    for (i, rank) in RANK_ARRAY.iter().rev().enumerate() {
+        if i > 0 {
+            fen.push('/');
+        }
+
+        let mut empty = 0;
+        for file in FILE_ARRAY {
+            match board[*rank][file] {
+                Some(piece) => {
+                    if empty > 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    fen.push(piece_to_fen(piece));
+                },
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            fen.push_str(&empty.to_string());
+        }
+    }
+
+    fen
+}
+
+///parses the piece placement field of a FEN
+pub(crate) fn parse_board(field: &str) -> Result<Board, FenError> {
+    let mut board = Board::from([[None; 8]; 8]);
+    let rows: Vec<&str> = field.split('/').collect();
+    if rows.len() != 8 {
+        return Err(FenError::InvalidBoard);
+    }
+
+    let mut kings = (0, 0);
+    for (row, rank) in rows.iter().zip(RANK_ARRAY.iter().rev()) {
+        let mut file = 0;
+        for c in row.chars() {
+            if let Some(empty) = c.to_digit(10) {
+                if !(1..=8).contains(&empty) {
+                    return Err(FenError::InvalidBoard);
+                }
+                file += empty as i32;
+                continue;
+            }
+
+            let square = Square {
+                file: File::try_from(file).map_err(|_| FenError::InvalidBoard)?,
+                rank: *rank,
+            };
+            let piece = piece_from_fen(c, square).ok_or(FenError::InvalidBoard)?;
+
+            match piece {
+                Piece { piece: PieceTypes::King, color: Color::White } => kings.0 += 1,
+                Piece { piece: PieceTypes::King, color: Color::Black } => kings.1 += 1,
+                Piece { piece: PieceTypes::Pawn(_), .. } if *rank == Rank::R1 || *rank == Rank::R8 =>
+                    return Err(FenError::InvalidBoard),
+                _ => {}
+            }
+
+            board.set(square, Some(piece));
+            file += 1;
+        }
+        if file != 8 {
+            return Err(FenError::InvalidBoard);
+        }
+    }
+
+    if kings != (1, 1) {
+        return Err(FenError::InvalidBoard);
+    }
+
+    Ok(board)
+}
+
+/**
+Parses the castling field in the standard KQkq notation or in X-FEN/Shredder-FEN where the rook is given by its file, e.g. HAha.
+K and Q stand for the outermost rook on that side of the king.
+*/
+fn parse_castling(field: &str, board: &Board) -> Result<Castling, FenError> {
+    let mut castling = Castling {
+        white: (None, None),
+        black: (None, None),
+    };
+    if field == "-" {
+        return Ok(castling);
+    }
+
+    for c in field.chars() {
+        let color = match c.is_ascii_uppercase() {
+            true => Color::White,
+            false => Color::Black,
+        };
+        let home_row = match color {
+            Color::White => Rank::R1,
+            Color::Black => Rank::R8,
+        };
+        let king = FILE_ARRAY.into_iter()
+            .find(|f| board[home_row][*f] == Some(Piece { piece: PieceTypes::King, color }))
+            .ok_or(FenError::InvalidCastling)?;
+        let rook = |file: &File| board[home_row][*file] == Some(Piece { piece: PieceTypes::Rook, color });
+
+        let rook_file = match c.to_ascii_lowercase() {
+            'k' => FILE_ARRAY.into_iter().rev().find(rook),
+            'q' => FILE_ARRAY.into_iter().find(rook),
+            f @ 'a'..='h' => Some(FILE_ARRAY[f as usize - 'a' as usize]).filter(rook),
+            _ => None,
+        }.ok_or(FenError::InvalidCastling)?;
+
+        let side = match rook_file.num().cmp(&king.num()) {
+            std::cmp::Ordering::Greater => CastlingSide::KingSide,
+            std::cmp::Ordering::Less => CastlingSide::QueenSide,
+            std::cmp::Ordering::Equal => return Err(FenError::InvalidCastling),
+        };
+        if (c == 'K' || c == 'k') && side != CastlingSide::KingSide || (c == 'Q' || c == 'q') && side != CastlingSide::QueenSide {
+            return Err(FenError::InvalidCastling);
+        }
+
+        let rights = match color {
+            Color::White => &mut castling.white,
+            Color::Black => &mut castling.black,
+        };
+        match side {
+            CastlingSide::KingSide => rights.0 = Some(rook_file),
+            CastlingSide::QueenSide => rights.1 = Some(rook_file),
+        }
+    }
+
+    Ok(castling)
+}
+
+///the castling field of a FEN. Rights with the outermost rook are written as KQkq and other rights with the file of the rook as in X-FEN.
+fn castling_to_string(castling: &Castling, board: &Board) -> String {
+    let mut field = String::new();
+    for (color, home_row) in [(Color::White, Rank::R1), (Color::Black, Rank::R8)] {
+        let rook = |file: &File| board[home_row][*file] == Some(Piece { piece: PieceTypes::Rook, color });
+        let (king_side, queen_side) = castling.rights(color);
+
+        let letters = [
+            (king_side, FILE_ARRAY.into_iter().rev().find(rook), 'k'),
+            (queen_side, FILE_ARRAY.into_iter().find(rook), 'q'),
+        ];
+        for (right, outermost, letter) in letters {
+            let Some(file) = right else { continue };
+            let letter = match outermost == Some(file) {
+                true => letter,
+                false => (b'a' + file.num() as u8) as char,
+            };
+            field.push(match color {
+                Color::White => letter.to_ascii_uppercase(),
+                Color::Black => letter,
+            });
+        }
+    }
+
+    if field.is_empty() {
+        field.push('-');
+    }
+    field
+}
+
+///checks if the castling rights can only come from a Chess960 position, with the king not on the e file or a rook not in the corner
+fn chess960_castling(castling: &Castling, board: &Board) -> bool {
+    [(Color::White, Rank::R1), (Color::Black, Rank::R8)].into_iter().any(|(color, home_row)| {
+        let (king_side, queen_side) = castling.rights(color);
+        let has_rights = king_side.is_some() || queen_side.is_some();
+        let king_on_e = board[home_row][File::E] == Some(Piece { piece: PieceTypes::King, color });
+
+        (has_rights && !king_on_e) || king_side.is_some_and(|f| f != File::H) || queen_side.is_some_and(|f| f != File::A)
+    })
+}
+
+///the FEN letter of a piece. Uppercase for white and lowercase for black.
+pub(crate) fn piece_to_fen(piece: Piece) -> char {
+    let c = match piece.piece {
+        PieceTypes::Pawn(_) => 'p',
+        PieceTypes::Knight => 'n',
+        PieceTypes::Bishop => 'b',
+        PieceTypes::Rook => 'r',
+        PieceTypes::Queen => 'q',
+        PieceTypes::King => 'k',
+    };
+    match piece.color {
+        Color::White => c.to_ascii_uppercase(),
+        Color::Black => c,
+    }
+}
+
+///the piece of a FEN letter. Pawns not on their starting rank are marked as moved.
+fn piece_from_fen(c: char, square: Square) -> Option<Piece> {
+    let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+    let piece = match c.to_ascii_lowercase() {
+        'p' => PieceTypes::Pawn(match color {
+            Color::White => square.rank != Rank::R2,
+            Color::Black => square.rank != Rank::R7,
+        }),
+        'n' => PieceTypes::Knight,
+        'b' => PieceTypes::Bishop,
+        'r' => PieceTypes::Rook,
+        'q' => PieceTypes::Queen,
+        'k' => PieceTypes::King,
+        _ => return None,
+    };
+    Some(Piece { piece, color })
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FenError {
+    MissingField,
+    TooManyFields,
+    InvalidBoard,
+    InvalidTurn,
+    InvalidCastling,
+    InvalidEnPassant,
+    InvalidClock,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FenError::MissingField => write!(f, "Missing field: the FEN needs at least the board, turn, castling and en passant fields"),
+            FenError::TooManyFields => write!(f, "Too many fields: the FEN has more than six fields"),
+            FenError::InvalidBoard => write!(f, "Invalid board: the board field does not describe a legal position"),
+            FenError::InvalidTurn => write!(f, "Invalid turn: the turn field has to be either w or b"),
+            FenError::InvalidCastling => write!(f, "Invalid castling: the castling field has to be - or a combination of KQkq or rook files matching the kings and rooks on the board"),
+            FenError::InvalidEnPassant => write!(f, "Invalid en passant: the en passant field has to be - or a square on the third or sixth rank"),
+            FenError::InvalidClock => write!(f, "Invalid clock: the halfmove clock and fullmove number have to be numbers and the fullmove number can't be 0"),
+        }
+    }
+}
+
+impl error::Error for FenError { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sq;
+    use crate::Move;
+
+    #[test]
+    fn starting_position() {
+        assert_eq!(Game::new().to_fen(), STARTING_FEN);
+
+        let game = Game::from_fen(STARTING_FEN).unwrap();
+        assert_eq!(game.board[Rank::R1][File::E], Some(Piece { piece: PieceTypes::King, color: Color::White }));
+        assert_eq!(game.board[Rank::R7][File::A], Some(Piece { piece: PieceTypes::Pawn(false), color: Color::Black }));
+        assert_eq!(game.castling, Game::new().castling);
+    }
+
+    #[test]
+    fn round_trip() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r3k2r/8/8/8/8/8/8/4K3 b kq - 12 40",
+        ] {
+            assert_eq!(Game::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn counters_follow_moves() {
+        let mut game = Game::new();
+
+        game.try_move(Move::Normal { from: sq(File::E, Rank::R2), to: sq(File::E, Rank::R4) }).unwrap();
+        assert_eq!(game.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+
+        game.try_move(Move::Normal { from: sq(File::G, Rank::R8), to: sq(File::F, Rank::R6) }).unwrap();
+        assert_eq!(game.to_fen(), "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2");
+    }
+
+    #[test]
+    fn short_fen_and_checkmate() {
+        let game = Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq -").unwrap();
+        assert_eq!(game.halfmove_clock, 0);
+        assert_eq!(game.fullmove_number, 1);
+        assert!(game.check);
+        assert_eq!(game.game_status, GameStatus::Checkmate(Color::Black));
+    }
+
+    #[test]
+    fn invalid_fens() {
+        assert_eq!(Game::from_fen("").err(), Some(FenError::MissingField));
+        assert_eq!(Game::from_fen("8/8/8/8/8/8/8/8 w - - 0 1").err(), Some(FenError::InvalidBoard));
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 x - - 0 1").err(), Some(FenError::InvalidTurn));
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w K - 0 1").err(), Some(FenError::InvalidCastling));
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - e4 0 1").err(), Some(FenError::InvalidEnPassant));
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - x 1").err(), Some(FenError::InvalidClock));
+        assert_eq!(Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1 x").err(), Some(FenError::TooManyFields));
+        assert_eq!(Game::from_fen("4k3/4R3/8/8/8/8/8/4K3 w - - 0 1").err(), Some(FenError::InvalidBoard));
+    }
+}
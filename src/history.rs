@@ -0,0 +1,223 @@
+use crate::{Castling, Color, Game, GameStatus, Move, Piece, PieceTypes};
+use crate::util::Square;
+use crate::movegen::castling_targets;
+
+///Everything that is needed to take back a move. The fields hold the state from before the move was made.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveRecord {
+    pub mv: Move,
+    ///the piece that was moved, the king for castling moves
+    pub piece: Piece,
+    ///the square the piece was moved from, the square of the king for castling moves
+    pub from: Square,
+    ///the piece that was captured and the square it was captured on, which differs from the target square for en passant
+    pub captured: Option<(Piece, Square)>,
+    pub castling: Castling,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    pub check: bool,
+    pub game_status: GameStatus,
+    pub draw_offer: Option<Color>,
+}
+
+impl Game {
+    /**
+    Takes back the last move and returns it. A pawn move waiting for promote is taken back as well.
+    The move can be played again with redo until a new move is made. Returns None if no moves have been made or if the game
+    ended without a move, by resignation, agreement, adjudication or on time, since taking back a move does not take back that ending.
+    */
+    pub fn undo(&mut self) -> Option<Move> {
+        if self.history.is_empty() || self.ended_without_move() {
+            return None;
+        }
+
+        //the position is only recorded once the turn is passed
+        if self.game_status != GameStatus::Promoting {
+            self.positions.pop();
+        }
+
+        let record = self.unmake_move()?;
+        self.redo_moves.push(record.mv);
+
+        //the time used is not given back but the clock is switched to the player in turn
+        if let Some(clock) = self.clock.as_mut() {
+            clock.start(self.turn);
+        }
+        Some(record.mv)
+    }
+
+    ///Plays the last move taken back with undo again and returns it. Returns None if there is nothing to redo or the move is refused.
+    pub fn redo(&mut self) -> Option<Move> {
+        let mv = self.redo_moves.pop()?;
+
+        let redo_moves = std::mem::take(&mut self.redo_moves);
+        let result = self.try_move(mv);
+        self.redo_moves = redo_moves;
+
+        match result {
+            Ok(()) => Some(mv),
+            Err(_) => {
+                self.redo_moves.push(mv);
+                None
+            },
+        }
+    }
+
+    ///the record of the move that has to be pushed before the move is made on the board.
+    pub(crate) fn move_record(&self, mv: Move) -> MoveRecord {
+        let (piece, from, captured) = match mv {
+            Move::Castle { side, .. } => {
+                let (king, _, _, _) = self.castling_squares(side).expect("a castling record needs the right to castle");
+                (Piece { piece: PieceTypes::King, color: self.turn }, king, None)
+            },
+            Move::Normal { from, to } | Move::Promotion { from, to, .. } => {
+                let captured = match self.board[to] {
+                    Some(piece) => Some((piece, to)),
+                    None => self.en_passant_capture(from, to).and_then(|s| self.board[s].map(|p| (p, s))),
+                };
+                (self.board[from].expect("a move record needs a piece to move"), from, captured)
+            },
+        };
+
+        MoveRecord {
+            mv,
+            piece,
+            from,
+            captured,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            check: self.check,
+            game_status: self.game_status,
+            draw_offer: self.draw_offer,
+        }
+    }
+
+    ///restores the board and state from before the last move without touching the position list or the redo moves.
+    pub(crate) fn unmake_move(&mut self) -> Option<MoveRecord> {
+        let record = self.history.pop()?;
+        self.moves.pop();
+
+        match record.mv {
+            Move::Castle { side, color } => {
+                let rank = record.from.rank;
+                let rook_from = record.castling.rook(color, side).expect("a castle is only made with the right to castle");
+                let (king_to, rook_to) = castling_targets(side);
+                let square = |file| Square { file, rank };
+
+                self.board.set(square(king_to), None);
+                self.board.set(square(rook_to), None);
+                self.board.set(record.from, Some(record.piece));
+                self.board.set(square(rook_from), Some(Piece { piece: PieceTypes::Rook, color }));
+            },
+            Move::Normal { from, to } | Move::Promotion { from, to, .. } => {
+                self.board.set(to, None);
+                self.board.set(from, Some(record.piece));
+            },
+        }
+
+        if let Some((piece, square)) = record.captured {
+            self.board.set(square, Some(piece));
+            self.captured.pop();
+        }
+
+        self.turn = record.piece.color;
+        self.castling = record.castling;
+        self.en_passant = record.en_passant;
+        self.halfmove_clock = record.halfmove_clock;
+        self.fullmove_number = record.fullmove_number;
+        self.check = record.check;
+        self.game_status = record.game_status;
+        self.draw_offer = record.draw_offer;
+
+        Some(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::play;
+    use crate::util::{File, Rank};
+
+    #[test]
+    fn undo_restores_every_position() {
+        let mut game = Game::new();
+        let sans = ["e4", "d5", "exd5", "Nf6", "Bb5+", "c6", "dxc6", "Qb6", "cxb7", "Kd8", "bxa8=Q", "e5", "Nf3", "Bd6", "O-O"];
+        let mut fens = vec![game.to_fen()];
+        for san in sans {
+            play(&mut game, san).unwrap();
+            fens.push(game.to_fen());
+        }
+
+        while game.undo().is_some() {
+            fens.pop();
+            assert_eq!(game.to_fen(), *fens.last().unwrap());
+        }
+        assert_eq!(game.captured, vec![]);
+        assert_eq!(game.moves, vec![]);
+
+        for _ in sans {
+            game.redo().unwrap();
+        }
+        assert_eq!(game.to_fen(), "Qnbk3r/p4ppp/1q1b1n2/1B2p3/8/5N2/PPPP1PPP/RNBQ1RK1 b - - 3 8");
+        assert_eq!(game.redo(), None);
+    }
+
+    #[test]
+    fn undo_en_passant_and_promotion() {
+        let mut game = Game::from_fen("4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let before = game.to_fen();
+        play(&mut game, "exd6").unwrap();
+        assert_eq!(game.captured.len(), 1);
+        assert_eq!(game.undo(), Some(Move::Normal { from: Square { file: File::E, rank: Rank::R5 }, to: Square { file: File::D, rank: Rank::R6 } }));
+        assert_eq!(game.to_fen(), before);
+        assert_eq!(game.captured, vec![]);
+
+        //a pawn waiting for promotion is taken back
+        let from = Square { file: File::B, rank: Rank::R7 };
+        let to = Square { file: File::B, rank: Rank::R8 };
+        game.try_move(Move::Normal { from, to }).unwrap();
+        assert_eq!(game.game_status, GameStatus::Promoting);
+        game.undo();
+        assert_eq!(game.game_status, GameStatus::Ongoing);
+        assert_eq!(game.to_fen(), before);
+
+        //after a promotion the promoted piece is turned back into a pawn
+        game.try_move(Move::Promotion { from, to, piece: PieceTypes::Knight }).unwrap();
+        game.undo();
+        assert_eq!(game.board[from], Some(Piece { piece: PieceTypes::Pawn(true), color: Color::White }));
+        assert_eq!(game.redo(), Some(Move::Promotion { from, to, piece: PieceTypes::Knight }));
+    }
+
+    #[test]
+    fn new_move_clears_redo() {
+        let mut game = Game::new();
+        play(&mut game, "e4 e5").unwrap();
+        game.undo();
+        play(&mut game, "c5").unwrap();
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.history.len(), 2);
+    }
+
+    #[test]
+    fn undo_after_checkmate_and_repetition() {
+        let mut game = Game::new();
+        play(&mut game, "f3 e5 g4 Qh4#").unwrap();
+        assert_eq!(game.game_status, GameStatus::Checkmate(Color::Black));
+        game.undo();
+        assert_eq!(game.game_status, GameStatus::Ongoing);
+        assert!(!game.check);
+
+        let mut game = Game::new();
+        play(&mut game, "Nf3 Nf6 Ng1 Ng8 Nf3 Nf6 Ng1").unwrap();
+        game.undo();
+        play(&mut game, "Ng1").unwrap();
+        assert_eq!(game.game_status, GameStatus::Ongoing);
+        play(&mut game, "Ng8").unwrap();
+        assert_eq!(game.game_status, GameStatus::Draw(crate::DrawReason::ThreefoldRepetition));
+    }
+}
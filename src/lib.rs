@@ -1,9 +1,33 @@
 pub mod util;
-use util::{Square, Rank, File, Board, Rows, FILE_ARRAY, RANK_ARRAY, BoardMove, get_square_array};
+pub mod fen;
+pub mod san;
+pub mod pgn;
+pub mod history;
+pub mod search;
+pub mod eval;
+pub mod uci;
+pub mod engine;
+pub mod clock;
+pub mod display;
+pub mod book;
+pub mod tablebase;
+mod movegen;
+mod bitboard;
+mod zobrist;
+mod perft;
+mod chess960;
+mod termination;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(test)]
+mod test_util;
+use util::{Square, Rank, File, Board, BoardMove, get_square_array};
+use history::MoveRecord;
 
 //TODO: think about if Copy and Clone are necessary just because compiler recommends it
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceTypes {
     Pawn(bool),
     Bishop,
@@ -14,6 +38,7 @@ pub enum PieceTypes {
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Color {
     White,
     Black,
@@ -29,58 +54,168 @@ impl Color {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Piece {
     pub piece: PieceTypes,
     pub color: Color,
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameStatus {
     Checkmate(Color),
+    Draw(DrawReason),
+    ///a player ran out of time, the color is the winner
+    Timeout(Color),
+    ///a player resigned, the color is the winner
+    Resigned(Color),
+    ///an arbiter ended the game, the color is the winner or None for a draw
+    Adjudicated(Option<Color>),
     Ongoing,
     Promoting,
 }
 
+///Why a game ended in a draw. Threefold repetition and the fifty move rule end the game automatically without a claim.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawReason {
+    Stalemate,
+    InsufficientMaterial,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    ///a player ran out of time but the opponent does not have the material to checkmate
+    TimeoutVsInsufficientMaterial,
+    ///a draw offer was accepted
+    Agreement,
+}
+
+///How a game ended. Returned by Game::result together with the winner.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Termination {
+    Checkmate,
+    Resignation,
+    Timeout,
+    Agreement,
+    Stalemate,
+    InsufficientMaterial,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    TimeoutVsInsufficientMaterial,
+    Adjudication,
+}
+
+///The result of a finished game. winner is None for a draw.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameResult {
+    pub winner: Option<Color>,
+    pub termination: Termination,
+}
+
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CastlingSide {
     KingSide,
     QueenSide,
 }
 
-#[derive(Debug)]
+///The castling rights of both players as the files of the rooks they can castle with, king side first and queen side second.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Castling {
-    pub white: (Option<CastlingSide>, Option<CastlingSide>),
-    pub black: (Option<CastlingSide>, Option<CastlingSide>),
+    pub white: (Option<File>, Option<File>),
+    pub black: (Option<File>, Option<File>),
 }
 
-#[derive(Copy, Clone, Debug)]
+impl Castling {
+    ///the rights of color
+    pub fn rights(&self, color: Color) -> (Option<File>, Option<File>) {
+        match color {
+            Color::White => self.white,
+            Color::Black => self.black,
+        }
+    }
+
+    ///the file of the rook color can castle with on side
+    pub fn rook(&self, color: Color, side: CastlingSide) -> Option<File> {
+        match side {
+            CastlingSide::KingSide => self.rights(color).0,
+            CastlingSide::QueenSide => self.rights(color).1,
+        }
+    }
+
+    fn rights_mut(&mut self, color: Color) -> &mut (Option<File>, Option<File>) {
+        match color {
+            Color::White => &mut self.white,
+            Color::Black => &mut self.black,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Move {
     Normal {
         from: Square,
         to: Square,
     },
+    ///a castle of the king of color. The squares of the king and rook come from the position it is played in.
     Castle {
         side: CastlingSide,
+        color: Color,
+    },
+    ///a pawn move to the last rank that promotes the pawn to piece in the same move.
+    ///A Normal move to the last rank instead puts the game in GameStatus::Promoting until Game::promote is called.
+    Promotion {
+        from: Square,
+        to: Square,
+        piece: PieceTypes,
     },
 }
 
 ///A chess game. All the data from the game is accessible in the fields of the struct but should only be mutated through the associated methods.
+#[derive(Clone)]
 pub struct Game {
     pub board: Board,
     pub turn: Color,
     pub captured: Vec<Piece>,
     pub castling: Castling,
-    //pub fifty_move_rule: u8,
-    /*pub en_passant_white: Option<Square>,
-    pub en_passant_black: Option<Square>,*/
+    ///the number of half moves since the last capture or pawn move.
+    pub halfmove_clock: u32,
+    ///the number of the current full move. Starts at 1 and is increased after every move by black.
+    pub fullmove_number: u32,
+    ///the square a pawn passed over when moving two squares in the last move. An opponent pawn can capture en passant by moving there.
+    pub en_passant: Option<Square>,
     pub check: bool,
     pub game_status: GameStatus,
+    ///the moves played in the game in order. Promotions are always recorded as Move::Promotion.
+    pub moves: Vec<Move>,
+    ///the FEN of the position the game started from.
+    pub initial_fen: String,
+    ///the Zobrist key of every position of the game, used for threefold repetition.
+    pub(crate) positions: Vec<u64>,
+    ///a record of every move made, used to undo them.
+    pub history: Vec<MoveRecord>,
+    ///the moves taken back with undo that can be played again with redo. Cleared when a new move is made.
+    pub(crate) redo_moves: Vec<Move>,
+    ///the clock of the game if it is played with time control. Set with set_clock.
+    pub clock: Option<clock::Clock>,
+    ///true for Chess960 games. Castling is then written as the king taking its own rook in UCI.
+    pub chess960: bool,
+    ///the player whose draw offer is waiting for an answer. Set with offer_draw and cleared when the opponent answers or makes a move.
+    pub draw_offer: Option<Color>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Game {
     pub fn new() -> Game {
-        Game {
+        let mut game = Game {
             board: {
                 [[
                     Some(Piece { piece: PieceTypes::Rook, color: Color::White }),
@@ -112,661 +247,340 @@ impl Game {
             turn: Color::White,
             captured: vec![],
             castling: Castling {
-                white: (Some(CastlingSide::KingSide), Some(CastlingSide::QueenSide)),
-                black: (Some(CastlingSide::KingSide), Some(CastlingSide::QueenSide)),
+                white: (Some(File::H), Some(File::A)),
+                black: (Some(File::H), Some(File::A)),
             },
-            /*fifty_move_rule: 0,
-            en_passant_black: None,
-            en_passant_white: None,*/
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            en_passant: None,
             check: false,
             game_status: GameStatus::Ongoing,
-        }
+            moves: vec![],
+            initial_fen: String::from(fen::STARTING_FEN),
+            positions: vec![],
+            history: vec![],
+            redo_moves: vec![],
+            clock: None,
+            chess960: false,
+            draw_offer: None,
+        };
+        game.positions.push(game.hash());
+        game
     }
 
     /**
     Tries making move described by coordinates. If unsuccessful no move will be made and you can try making a leagal move again.
     The return vale is an option that shows if a player has won this turn and in that case which color won.
+    If a pawn is moved to the last rank with a Normal move the game goes into GameStatus::Promoting and the turn is not passed until promote is called.
     */
     pub fn try_move(&mut self, mv: Move) -> Result<(), MoveError> {
-        match mv {
-            Move::Castle { side } => {
-                let (home_row, possible_castles): (Rank, _) = match self.turn {
-                    Color::White => (Rank::R1, self.castling.white),
-                    Color::Black => (Rank::R8, self.castling.black),
-                };
-
-                match possible_castles {
-                    (Some(CastlingSide::KingSide), ..) if side == CastlingSide::KingSide => {
-                        if self.board[home_row][File::F].is_some() || self.board[home_row][File::G].is_some() {
-                            return Err(MoveError::Collision);
-                        }
-
-                        let test_move = Move::Normal {
-                            from: Square { file: File::E, rank: home_row },
-                            to: Square { file: File::F, rank: home_row },
-                        };
-                        if self.check_check(mv, self.turn) || self.check_check(test_move, self.turn) || self.check {
-                            return Err(MoveError::CastlingError);
-                        }
-
-                        if self.check_check(mv, self.turn.opposite()) {
-                            self.check = true;
-                        }
-                        else { self.check = false; }
-
-                        if self.checkmate_check(mv, self.turn.opposite()) {
-                            self.game_status = GameStatus::Checkmate(self.turn);
-                        }
-
-                        match self.turn {
-                            Color::White => { self.castling.white = (None, None); },
-                            Color::Black => { self.castling.black = (None, None); },
-                        }
-
-                        self.board[home_row][File::G] = self.board[home_row][File::E];
-                        self.board[home_row][File::F] = self.board[home_row][File::H];
-                        self.board[home_row][File::H] = None;
-                        self.board[home_row][File::E] = None;
-                    },
-                    (.., Some(CastlingSide::QueenSide)) if side == CastlingSide::QueenSide => {
-                        if self.board[home_row][File::D].is_some() || self.board[home_row][File::C].is_some() || self.board[home_row][File::B].is_some() {
-                            return Err(MoveError::Collision);
-                        }
-
-                        let test_move = Move::Normal {
-                            from: Square { file: File::E, rank: home_row },
-                            to: Square { file: File::D, rank: home_row },
-                        };
-                        if self.check_check(mv, self.turn) || self.check_check(test_move, self.turn) || self.check {
-                            return Err(MoveError::CastlingError);
-                        }
-
-                        if self.check_check(mv, self.turn.opposite()) {
-                            self.check = true;
-                        }
-                        else { self.check = false; }
-
-                        if self.checkmate_check(mv, self.turn.opposite()) {
-                            self.game_status = GameStatus::Checkmate(self.turn);
-                        }
-
-                        match self.turn {
-                            Color::White => { self.castling.white = (None, None); },
-                            Color::Black => { self.castling.black = (None, None); },
-                        }
-
-                        self.board[home_row][File::C] = self.board[home_row][File::E];
-                        self.board[home_row][File::D] = self.board[home_row][File::A];
-                        self.board[home_row][File::A] = None;
-                        self.board[home_row][File::E] = None;
-                    },
-                    _ => return Err(MoveError::CastlingError),
-                }
-
+        if self.check_time() {
+            return Err(MoveError::Timeout);
+        }
+        if self.result().is_some() {
+            return Err(MoveError::GameOver);
+        }
+        if self.game_status == GameStatus::Promoting {
+            return Err(MoveError::PromotionPending);
+        }
+        if let Move::Promotion { piece, .. } = mv {
+            if !Self::promotion_piece(piece) {
+                return Err(MoveError::InvalidPromotion);
+            }
+        }
 
+        let legal_moves = self.legal_moves();
+        //a king move that is not legal on its own can be a castle written as the king move, e.g. e1g1
+        let mv = match mv {
+            Move::Normal { from, to } if !legal_moves.contains(&mv) => match self.castling_side(from, to) {
+                Some(side) if legal_moves.contains(&Move::Castle { side, color: self.turn }) => Move::Castle { side, color: self.turn },
+                _ => mv,
             },
-            Move::Normal { from, to } => {
-                let mut origin = match self.board[from] {
-                    Some( Piece { color: c, .. } ) if c != self.turn => return Err(MoveError::OpponentPiece),
-                    Some(o) => o,
-                    None => return Err(MoveError::EmptySquare),
-                };
-
-                let captured = match self.board[to] {
-                    Some(Piece { color: c, .. }) if c == origin.color =>
-                        return Err(MoveError::Collision),
-                    destination @ _ => destination
-                };
+            _ => mv,
+        };
+        let promotion = match mv {
+            Move::Normal { from, to } => legal_moves.contains(&Move::Promotion { from, to, piece: PieceTypes::Queen }),
+            _ => false,
+        };
+        if !promotion && !legal_moves.contains(&mv) {
+            return Err(self.move_error(mv));
+        }
 
+        self.redo_moves.clear();
+        self.make_move(mv);
 
-                //TODO: return errors if moves collide, are not possible for the specific piece or lead to check.
-                match origin.piece {
-                    PieceTypes::Bishop => {
-                        if from.file.abs_diff(to.file) != from.rank.abs_diff(to.rank) {
-                            return Err(MoveError::WrongPieceMovement);
-                        }
-                        else if self.collision_check_line(from, to, self.turn) {
-                            return Err(MoveError::Collision);
-                        }
-                    },
-                    PieceTypes::King => {
-                        if !((from.file).abs_diff(to.file) <= 1 && (from.rank).abs_diff(to.rank) <= 1) {
-                            return Err(MoveError::WrongPieceMovement);
-                        }
-                        else if self.collision_check(to, self.turn) {
-                            return Err(MoveError::Collision);
-                        }
+        //a Normal move to the last rank waits for promote before the turn is passed
+        if promotion {
+            self.game_status = GameStatus::Promoting;
+            return Ok(());
+        }
 
-                        match from.rank {
-                            Rank::R1 => { self.castling.white = (None, None); },
-                            Rank::R8 => { self.castling.black = (None, None); },
-                            _ => {}
-                        }
+        self.end_turn();
 
-                    },
-                    PieceTypes::Queen => {
-                        if from.file.abs_diff(to.file) == from.rank.abs_diff(to.rank) ||
-                            (from.file.abs_diff(to.file) > 0) ^ (from.rank.abs_diff(to.rank) > 0) {
-                            if self.collision_check_line(from, to, self.turn) {
-                                return Err(MoveError::Collision);
-                            }
-                        } else {
-                            return Err(MoveError::WrongPieceMovement);
-                        }
-                    },
-                    PieceTypes::Rook => {
-                        if !((from.file.abs_diff(to.file) > 0) ^ (from.rank.abs_diff(to.rank) > 0)){
-                            return Err(MoveError::WrongPieceMovement);
-                        }
-                        else if self.collision_check_line(from, to, self.turn) {
-                            return Err(MoveError::Collision);
-                        }
-                        match from.file {
-                            File::A => {
-                                match from.rank {
-                                    Rank::R1 => { self.castling.white.1 = None; },
-                                    Rank::R8 => { self.castling.black.1 = None; },
-                                    _ => {}
-                                }
-                            },
-                            File::H => {
-                                match from.rank {
-                                    Rank::R1 => { self.castling.white.0 = None; },
-                                    Rank::R8 => { self.castling.black.0 = None; },
-                                    _ => {}
-                                }
-                            },
-                            _ => {}
-                        }
-                    },
-                    PieceTypes::Pawn(moved) => {
-                        let multiply: i32 = match self.turn {
-                            Color::White => 1,
-                            Color::Black => -1
-                        };
-
-                        if !((to.rank.abs_diff(from.rank) == 1
-                            && to.file.abs_diff(from.file) <= 1)
-                            || (to.rank.abs_diff(from.rank) == 2
-                            && to.file.abs_diff(from.file) == 0)) {
-                            return Err(MoveError::WrongPieceMovement);
-                        }
+        Ok(())
+    }
 
-                        if to.file.abs_diff(from.file) == 1 && to.rank.abs_diff(from.rank) == 1 {
-                            if self.board[to].is_none() {
-                                return Err(MoveError::WrongPieceMovement);
-                            }
-                        }
+    ///makes the move on the board and updates the castling rights, en passant square, halfmove clock, captured pieces, move list and history. Does not check if the move is legal.
+    pub(crate) fn make_move(&mut self, mv: Move) {
+        let record = self.move_record(mv);
 
-                        if self.board[Rank::try_from(multiply + i32::from(from.rank)).unwrap()][from.file].is_some()
-                            && to.file.abs_diff(from.file) == 0 {
-                            return Err(MoveError::Collision);
-                        }
+        match mv {
+            Move::Castle { .. } => {
+                self.board = self.board_after(mv);
+                *self.castling.rights_mut(self.turn) = (None, None);
 
-                        if self.collision_check(to, self.turn) && to.file.abs_diff(from.file) == 1 {
-                            return Err(MoveError::Collision);
-                        }
+                self.en_passant = None;
+                self.halfmove_clock += 1;
+            },
+            Move::Normal { from, to } | Move::Promotion { from, to, .. } => {
+                let pawn = matches!(record.piece.piece, PieceTypes::Pawn(_));
+                let captured = record.captured.map(|(piece, _)| piece);
 
-                        if to.rank.abs_diff(from.rank) == 2 {
-                            if moved { return Err(MoveError::PawnDubbleMove); }
-                            if self.board[to].is_some() {
-                                return Err(MoveError::Collision);
-                            }
-                            /*else if from.rank.num() == origin_row {
-                                self.en_passant_black = Some(to);
-                                //TODO: add en passant
-                            }*/
-                        }
-                        //checks for promotions
-                        /*if origin_row - multiply == to.rank.num() {
-                            //TODO: add promotions
-                            //IDEA: use a game state enum to indicate when promotion happens.
-                        }*/
-                        origin = Piece { piece: PieceTypes::Pawn(true), color: origin.color };
-                    },
-                    PieceTypes::Knight => {
-                        if !(((from.file.abs_diff(to.file) == 2)
-                            && (from.rank.abs_diff(to.rank) == 1)) ^
-                            ((from.file.abs_diff(to.file) == 1)
-                            && (from.rank.abs_diff(to.rank) == 2))) {
-                            return Err(MoveError::WrongPieceMovement);
-                        }
-                        else if self.collision_check(to, self.turn) {
-                            return Err(MoveError::Collision);
-                        }
-                    },
-                }
+                self.board = self.board_after(mv);
 
-                if self.check_check(mv, self.turn) {
-                    return Err(MoveError::SelfCheck);
+                if pawn || captured.is_some() {
+                    self.halfmove_clock = 0;
                 }
+                else { self.halfmove_clock += 1; }
 
                 if let Some(c) = captured {
                     self.captured.push(c);
                 }
 
-                if self.check_check(mv, self.turn.opposite()) {
-                    self.check = true;
-                }
-                else { self.check = false; }
+                self.en_passant = match pawn && from.rank.abs_diff(to.rank) == 2 {
+                    true => Some(Square {
+                        file: from.file,
+                        rank: Rank::try_from((from.rank.num() + to.rank.num()) / 2).unwrap(),
+                    }),
+                    false => None,
+                };
 
-                if self.checkmate_check(mv, self.turn.opposite()) {
-                    self.game_status = GameStatus::Checkmate(self.turn);
+                //moving the king or a rook or capturing a rook loses the castling rights
+                if record.piece.piece == PieceTypes::King {
+                    *self.castling.rights_mut(self.turn) = (None, None);
+                }
+                for square in [from, to] {
+                    for (color, rank) in [(Color::White, Rank::R1), (Color::Black, Rank::R8)] {
+                        let rights = self.castling.rights_mut(color);
+                        if square.rank != rank {
+                            continue;
+                        }
+                        if rights.0 == Some(square.file) { rights.0 = None; }
+                        if rights.1 == Some(square.file) { rights.1 = None; }
+                    }
                 }
-
-                self.board[to] = Some(origin);
-                self.board[from] = None;
             },
         }
 
-        self.turn = self.turn.opposite();
-
-        //TODO: fifty move rule
-        //TODO: make draw possible
-
-        Ok(())
+        self.moves.push(mv);
+        self.history.push(record);
     }
 
-    ///checks for collisions with pieces of the color color or multiple collisions with the other color in a line from origin to destination. Returns the sqaure where the piece cant go becasuse of the collison. Can input out of bounds cords and it will stop at the edge
-    fn collision_check_line(&self, from: Square, to: Square, color: Color) -> bool {
-        let direction: (i32, i32) = (
-            (to.file.num() - from.file.num()).signum(),
-            (to.rank.num() - from.rank.num()).signum()
-        );
-
-        //let t_2 = (from.file.num(), from.rank.num(), to.file.num(), to.rank.num());
+    /**
+    Promotes the pawn that has reached the last rank to piece. Can only be called when the game status is GameStatus::Promoting
+    and piece has to be a queen, rook, bishop or knight. If successful the turn is passed to the other player.
+    */
+    pub fn promote(&mut self, piece: PieceTypes) -> Result<(), MoveError> {
+        if self.check_time() {
+            return Err(MoveError::Timeout);
+        }
+        if self.result().is_some() {
+            return Err(MoveError::GameOver);
+        }
+        if self.game_status != GameStatus::Promoting {
+            return Err(MoveError::NotPromoting);
+        }
+        if !Self::promotion_piece(piece) {
+            return Err(MoveError::InvalidPromotion);
+        }
 
-        let range =
-            if direction.0 != 0 { from.file.abs_diff(to.file) }
-            else { from.rank.abs_diff(to.rank) };
+        let last_row = match self.turn {
+            Color::White => Rank::R8,
+            Color::Black => Rank::R1,
+        };
+        let square = get_square_array()
+            .into_iter()
+            .find(|s| s.rank == last_row && matches!(
+                self.board[*s],
+                Some(Piece { piece: PieceTypes::Pawn(_), color: c }) if c == self.turn
+            ))
+            .ok_or(MoveError::NotPromoting)?;
+
+        self.board.set(square, Some(Piece { piece, color: self.turn }));
+        if let Some(Move::Normal { from, to }) = self.moves.pop() {
+            self.moves.push(Move::Promotion { from, to, piece });
+            if let Some(record) = self.history.last_mut() {
+                record.mv = Move::Promotion { from, to, piece };
+            }
+        }
+        self.game_status = GameStatus::Ongoing;
 
-        /*
-        if a enemy is encountered a collison should only be reported the next square
-        if that exsist therfore we use enemy counter to keep track of that has happened.
-        */
-        let mut enemy_counter = false;
-        for i in 1..=range {
-            let x = from.file.num() + direction.0 * i;
-            let y = from.rank.num() + direction.1 * i;
+        self.end_turn();
 
-            let square = Square::try_from((x, y)).unwrap();
+        Ok(())
+    }
 
-            if enemy_counter {
-                return true
-            }
+    ///returns the square of the pawn that gets captured if moving the piece in from to to is an en passant capture.
+    pub(crate) fn en_passant_capture(&self, from: Square, to: Square) -> Option<Square> {
+        let (from_row, to_row) = match self.board[from] {
+            Some(Piece { piece: PieceTypes::Pawn(_), color: Color::White }) => (Rank::R5, Rank::R6),
+            Some(Piece { piece: PieceTypes::Pawn(_), color: Color::Black }) => (Rank::R4, Rank::R3),
+            _ => return None,
+        };
 
-            if let Some(piece) = self.board[square] {
-                if piece.color == color {
-                    return true
-                }
-                else { enemy_counter = true; }
-            }
+        if self.en_passant != Some(to) || from.rank != from_row || to.rank != to_row || from.file.abs_diff(to.file) != 1 {
+            return None;
         }
-        false
-    }
 
-    ///checks for collision for a piece of color color in the position x, y.
-    fn collision_check(&self, square: Square, color: Color) -> bool {
-        match self.board[square] {
-            Some(Piece { color: c, .. }) if c == color => true,
-            _ => false
-        }
+        Some(Square { file: to.file, rank: from.rank })
     }
 
-    ///the color is who is in check. Will just panic if its passed a invalid move
-    fn check_check(&mut self, mv: Move, color: Color) -> bool {
-        let temp_board = self.board.clone();
-
-        match mv {
-            Move::Normal { from, to } => {
-                match self.board[to] {
-                    Some( Piece { piece: PieceTypes::King, color: c } ) if self.turn.opposite() == c => return true,
-                    _ => {}
-                }
-
-                self.board[to] = Some(self.board[from].unwrap());
-                self.board[from] = None;
-            },
-            Move::Castle { side } => {
-                let (home_row, possible_castles): (Rank, _) = match self.turn {
-                    Color::White => (Rank::R1, &self.castling.white),
-                    Color::Black => (Rank::R8, &self.castling.black),
-                };
+    ///the pieces a pawn is allowed to promote to
+    fn promotion_piece(piece: PieceTypes) -> bool {
+        matches!(piece, PieceTypes::Queen | PieceTypes::Rook | PieceTypes::Bishop | PieceTypes::Knight)
+    }
 
-                match possible_castles {
-                    (Some(CastlingSide::KingSide), ..) if side == CastlingSide::KingSide => {
-                        self.board[home_row][File::G] = self.board[home_row][File::E];
-                        self.board[home_row][File::F] = self.board[home_row][File::H];
-                        self.board[home_row][File::H] = None;
-                        self.board[home_row][File::E] = None;
-                    },
-                    (.., Some(CastlingSide::QueenSide)) if side == CastlingSide::QueenSide => {
-                        self.board[home_row][File::C] = self.board[home_row][File::E];
-                        self.board[home_row][File::D] = self.board[home_row][File::A];
-                        self.board[home_row][File::A] = None;
-                        self.board[home_row][File::E] = None;
-                    },
-                    _ => return false,
-                }
-            }
+    ///passes the turn and updates check and the game status for the opponent. Has to be called after the move is made on the board.
+    fn end_turn(&mut self) {
+        if self.turn == Color::Black {
+            self.fullmove_number += 1;
         }
 
-        let mut king_pos = None;
-        for square in get_square_array() {
-            if let Some(piece) = self.board[square] {
-                //println!("{:?}, {:?}", piece.piece == PieceTypes::King, piece.color == color);
-                if piece.piece == PieceTypes::King && piece.color == color {
-                    king_pos = Some(square);
-                }
-            }
+        //making a move declines the draw offer of the opponent
+        if self.draw_offer == Some(self.turn.opposite()) {
+            self.draw_offer = None;
         }
-        //println!();
-        //println!("move: {:?}", mv);
-        assert!(king_pos.is_some());
-        let mut result = false;
+        self.turn = self.turn.opposite();
 
-        for square in get_square_array() {
-            if let Some(piece) = self.board[square] {
-                if piece.color == color {
-                    continue;
-                }
-            }
-            if let Ok((moves, _)) = self.possible_moves(square, false) {
-                if moves[king_pos.unwrap()].is_some() {
-                    //println!("move_2: {:?}", moves[king_pos.unwrap()]);
-                    result = true;
-                    break;
-                }
+        self.update_status();
+
+        if let Some(clock) = self.clock.as_mut() {
+            clock.press();
+            if self.game_status != GameStatus::Ongoing {
+                clock.stop();
             }
         }
-
-        self.board = temp_board;
-
-        result
+        self.check_time();
     }
 
-    ///the color is who is in check. Will just panic if its passed a invalid move
-    fn checkmate_check(&mut self, mv: Move, color: Color) -> bool {
-        if !self.check {
-            return false;
-        }
+    ///updates check and the game status for the player in turn and records the position for threefold repetition.
+    pub(crate) fn update_status(&mut self) {
+        self.check = self.in_check(self.turn);
 
-        let temp_board = self.board.clone();
+        let position = self.hash();
+        let repetitions = self.positions.iter().filter(|p| **p == position).count() + 1;
+        self.positions.push(position);
 
-        match mv {
-            Move::Normal { from, to } => {
-                self.board[to] = Some(self.board[from].unwrap());
-                self.board[from] = None;
-            },
-            Move::Castle { side } => {
-                let (home_row, possible_castles): (Rank, _) = match self.turn {
-                    Color::White => (Rank::R1, &self.castling.white),
-                    Color::Black => (Rank::R8, &self.castling.black),
-                };
+        let no_moves = self.legal_moves().is_empty();
 
-                match possible_castles {
-                    (Some(CastlingSide::KingSide), ..) if side == CastlingSide::KingSide => {
-                        self.board[home_row][File::G] = self.board[home_row][File::E];
-                        self.board[home_row][File::F] = self.board[home_row][File::H];
-                        self.board[home_row][File::H] = None;
-                        self.board[home_row][File::E] = None;
-                    },
-                    (.., Some(CastlingSide::QueenSide)) if side == CastlingSide::QueenSide => {
-                        self.board[home_row][File::C] = self.board[home_row][File::E];
-                        self.board[home_row][File::D] = self.board[home_row][File::A];
-                        self.board[home_row][File::A] = None;
-                        self.board[home_row][File::E] = None;
-                    },
-                    _ => panic!(),
-                }
-            }
+        self.game_status = if no_moves && self.check {
+            GameStatus::Checkmate(self.turn.opposite())
+        }
+        else if no_moves {
+            GameStatus::Draw(DrawReason::Stalemate)
+        }
+        else if self.insufficient_material() {
+            GameStatus::Draw(DrawReason::InsufficientMaterial)
+        }
+        else if repetitions >= 3 {
+            GameStatus::Draw(DrawReason::ThreefoldRepetition)
         }
+        else if self.halfmove_clock >= 100 {
+            GameStatus::Draw(DrawReason::FiftyMoveRule)
+        }
+        else {
+            GameStatus::Ongoing
+        };
+    }
 
-        let mut result = true;
+    ///checks if neither player can checkmate. That is the case with only kings and at most one knight or only bishops on squares of the same color.
+    pub(crate) fn insufficient_material(&self) -> bool {
+        let mut knights = 0;
+        let mut bishop_colors = (false, false);
 
         for square in get_square_array() {
-            if let Some(piece) = self.board[square] {
-                if piece.color != color {
-                    continue;
-                }
-            }
-            //ignore castles since they cant be done if you are in check.
-            if let Ok((moves, _)) = self.possible_moves(square, false) {
-                for square_1 in get_square_array() {
-                    if moves[square_1].is_some() {
-                        let test_move = Move::Normal {
-                            from: square,
-                            to: square_1,
-                        };
-                        result = !self.check_check(test_move, color);
+            match self.board[square] {
+                Some(Piece { piece: PieceTypes::King, .. }) | None => {},
+                Some(Piece { piece: PieceTypes::Knight, .. }) => knights += 1,
+                Some(Piece { piece: PieceTypes::Bishop, .. }) => {
+                    if (square.file.num() + square.rank.num()) % 2 == 0 {
+                        bishop_colors.0 = true;
                     }
-                }
+                    else { bishop_colors.1 = true; }
+                },
+                Some(_) => return false,
             }
         }
 
-        self.board = temp_board;
-
-        result
-    }
-
-    fn possible_moves_directions(
-        &mut self,
-        color: Color,
-        from: Square,
-        possible_board: &mut BoardMove,
-        directions: Vec<(i32, i32)>,
-        check_checks: bool
-    ) {
-        for direction in directions {
-            for i in 1..=8 {
-                let x_1 = from.file.num() + direction.0 * i;
-                let y_1 = from.rank.num() + direction.1 * i;
-
-                if let Ok(square) = Square::try_from((x_1, y_1)) {
-                    match self.board[square] {
-                        Some( Piece { color: c, .. } ) if c == color => break,
-                        Some( Piece { color: c, .. } ) if c != color => {
-                            self.test_move(possible_board, x_1, y_1, from, color, check_checks);
-                            break;
-                        },
-                        None => self.test_move(possible_board, x_1, y_1, from, color, check_checks),
-                        Some(_) => unreachable!()
-                    }
-                }
-
-                self.test_move(possible_board, x_1, y_1, from, color, check_checks);
-            }
+        match bishop_colors {
+            (false, false) => knights <= 1,
+            (true, true) => false,
+            _ => knights == 0,
         }
     }
 
-    fn test_move(&mut self, possible_board: &mut BoardMove, x: i32, y: i32, from: Square, color: Color, check_checks: bool) {
-        if let Ok(square) = Square::try_from((x, y)) {
-            if !self.collision_check(square, color) {
-                let test_move = Move::Normal {
-                    from,
-                    to: square,
-                };
-                if !check_checks || !self.check_check(test_move, self.turn) {
-                    possible_board[square] = Some(test_move);
-                }
-            }
+    /// returns a board with all the legal moves for the piece in the from Square. It is a filter over legal_moves.
+    /// Potential castle moves are returned in a seperate Vec when from is the square of the king.
+    /// Promotions are put on the board as Normal moves which puts the game in GameStatus::Promoting when they are played.
+    /// Does not mutate the game so it can be called through a shared reference.
+    pub fn possible_moves(&self, from: Square) -> Result<(BoardMove, Vec<Move>), MoveError> {
+        let piece = self.board[from].ok_or(MoveError::EmptySquare)?;
+        if piece.color != self.turn {
+            return Err(MoveError::OpponentPiece);
         }
-        //if (x > 7 || x < 0 || y > 7 || y < 0) && test { println!("{:?}, {}, {}", from, x, y) }
-    }
 
-    /// returns a board with all the normal moves possible for the piece in the from Square.
-    /// potential Castelmoves are returned in a seperate Vec.
-    /// If check_checks is true the function will check if the move leads to check and if it does it will not be included in the return value. 
-    /// this is only false when calling the function internaly to prevent stack overflow.
-    /// TLDR: check_checks should always be true when calling this function.
-    pub fn possible_moves(&mut self, from: Square, check_checks: bool) -> Result<(BoardMove, Vec<Move>), MoveError> {
-        let piece = self.board[from].map_or(Err(MoveError::EmptySquare), |p| Ok(p))?;
         let mut possible_board = BoardMove::from([[None; 8]; 8]);
         let mut castles: Vec<Move> = vec![];
 
-        let straight_directions: Vec<(i32, i32)> = vec![(1, 0), (0, 1), (-1, 0), (0, -1)];
-        let diagonal_directions: Vec<(i32, i32)> = vec![(1, 1), (-1, 1), (-1, -1), (1, -1)];
-        match piece.piece {
-            PieceTypes::Knight => {
-                for direction in straight_directions {
-                    let x_temp: i32 = direction.0 * 2 + from.file.num();
-                    let y_temp: i32 = direction.1 * 2 + from.rank.num();
-
-                    let x_1 = x_temp + direction.1;
-                    let y_1 = y_temp + direction.0;
-                    self.test_move(&mut possible_board, x_1, y_1, from, piece.color, check_checks);
-
-                    let x_2 = x_temp - direction.1;
-                    let y_2 = y_temp - direction.0;
-                    self.test_move(&mut possible_board, x_2, y_2, from, piece.color, check_checks);
-                }
-            },
-            PieceTypes::King => {
-                for direction in [straight_directions, diagonal_directions].concat() {
-                    let x_1 = from.file.num() + direction.0;
-                    let y_1 = from.rank.num() + direction.1;
-
-                    self.test_move(&mut possible_board, x_1, y_1, from, piece.color, check_checks);
-                }
-            },
-            PieceTypes::Bishop => {
-                self.possible_moves_directions(piece.color, from, &mut possible_board, diagonal_directions, check_checks);
-            },
-            PieceTypes::Rook => {
-                self.possible_moves_directions(piece.color, from, &mut possible_board, straight_directions, check_checks);
-
-                let (home_row, possible_castles): (Rank, _) = match piece.color {
-                    Color::White => (Rank::R1, self.castling.white),
-                    Color::Black => (Rank::R8, self.castling.black),
-                };
-
-                if let (Some(CastlingSide::KingSide), ..) = possible_castles {
-                    'label: {
-                        //println!("{:?}", self.castling);
-                        let test_move = Move::Normal {
-                            from: Square { file: File::E, rank: home_row },
-                            to: Square { file: File::F, rank: home_row },
-                        };
-                        let test_move_1 = Move::Normal {
-                            from: Square { file: File::E, rank: home_row },
-                            to: Square { file: File::G, rank: home_row },
-                        };
-                        if self.board[home_row][File::F].is_some()
-                            || self.board[home_row][File::G].is_some()
-                            || self.check_check(test_move, self.turn)
-                            || self.check_check(test_move_1, self.turn)
-                            || self.check {
-                            break 'label;
-                        }
-
-                        castles.push(Move::Castle {
-                            side: CastlingSide::KingSide,
-                        });
-                    }
-                }
-                if let (.., Some(CastlingSide::QueenSide)) = possible_castles {
-                    'label: {
-                        let test_move = Move::Normal {
-                            from: Square { file: File::E, rank: home_row },
-                            to: Square { file: File::D, rank: home_row },
-                        };
-                        let test_move_1 = Move::Normal {
-                            from: Square { file: File::E, rank: home_row },
-                            to: Square { file: File::C, rank: home_row },
-                        };
-                        if self.board[home_row][File::D].is_some()
-                            || self.board[home_row][File::C].is_some()
-                            || self.board[home_row][File::B].is_some()
-                            || self.check_check(test_move, self.turn)
-                            || self.check_check(test_move_1, self.turn)
-                            || self.check {
-                            break 'label;
-                        }
-
-                        castles.push(Move::Castle {
-                            side: CastlingSide::QueenSide,
-                        });
-                    }
-                }
-            },
-            PieceTypes::Queen => {
-                self.possible_moves_directions(piece.color, from, &mut possible_board, [straight_directions, diagonal_directions].concat(), check_checks);
-            },
-            PieceTypes::Pawn(moved) => {
-                let movement_direction: i32 = match piece.color {
-                    Color::Black => -1,
-                    Color::White => 1,
-                };
-
-                //TODO: add en passant
-                //checks one or two moves forward
-                let y_1: i32 = from.rank.num() + movement_direction;
-
-                if let Ok(rank_1) = Rank::try_from(y_1) {
-                    if self.board[rank_1][from.file].is_none() {
-                        self.test_move(&mut possible_board, from.file.into(), y_1, from, piece.color, check_checks);
-
-                        let y_2: i32 = from.rank.num() + movement_direction * 2;
-
-                        if let Ok(rank_2) = Rank::try_from(y_2) {
-                            if !moved && self.board[rank_2][from.file].is_none() {
-                                self.test_move(&mut possible_board, from.file.into(), y_2, from, piece.color, check_checks);
-                            }
-                        }
-                    }
-                }
-
-                //checks for enemy's in the diagonal
-                let x_3 = from.file.num() + 1;
-                if let Ok(square) = Square::try_from((x_3, y_1)) {
-                    if let Some(enemy) = self.board[square] {
-                        if enemy.color != piece.color {
-                            self.test_move(&mut possible_board, x_3, y_1, from, piece.color, check_checks);
-                        }
-                    }
-                }
-
-                let x_4 = from.file.num() - 1;
-                if let Ok(square) = Square::try_from((x_4, y_1)) {
-                    if let Some(enemy) = self.board[square] {
-                        if enemy.color != piece.color {
-                            self.test_move(&mut possible_board, x_4, y_1, from, piece.color, check_checks);
-                        }
-                    }
-                }
+        for mv in self.legal_moves() {
+            match mv {
+                Move::Normal { from: f, to } | Move::Promotion { from: f, to, .. } if f == from => {
+                    possible_board[to] = Some(Move::Normal { from, to });
+                },
+                Move::Castle { .. } if piece.piece == PieceTypes::King => castles.push(mv),
+                _ => {}
             }
         }
-        Ok((possible_board, castles))
-    }
-
-    /*pub fn translate_move_to_san(&self, mv: Moves) -> &str {
-        todo!()
-    }
 
-    pub fn translate_move_from_san(input: &str) -> (usize, usize, usize, usize) {
-        todo!()
-    }
-
-    pub fn translate_game_from_fen(input: &str) -> Game {
-        todo!()
+        Ok((possible_board, castles))
     }
-
-    pub fn translate_game_to_fen(&self) -> &str {
-        todo!()
-    }*/
 }
 
-//TODO read how to and then make a proper error implementation with std::error::Error.
-#[derive(Debug, PartialEq)]
+///Why a move was rejected. The errors that are caused by a piece on the board carry the square of that piece.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MoveError {
     OpponentPiece,
     EmptySquare,
     WrongPieceMovement,
-    Collision,
+    ///the piece on at is in the way, either between the squares of the move or as an own piece on the target square
+    Collision { at: Square },
     PawnDubbleMove,
-    CastlingError,
-    SelfCheck,
-    None,
+    ///the move would leave the own king in check by the piece on attacker
+    SelfCheck { attacker: Square },
+    ///the moved piece is pinned to its king by the piece on by
+    Pinned { by: Square },
+    CastlingError { reason: CastlingBlock },
+    PromotionPending,
+    NotPromoting,
+    InvalidPromotion,
+    Timeout,
+    ///the game has already ended, see Game::result
+    GameOver,
+    ///there is no draw offer from the opponent to answer
+    NoDrawOffer,
+}
+
+///Why a castle is not possible. A castle with a piece between the king and the rook is a MoveError::Collision instead.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CastlingBlock {
+    ///the king or the rook has moved or the rook was captured
+    NoRight,
+    ///the king is in check by the piece on attacker
+    InCheck { attacker: Square },
+    ///the king would pass over or end on square, which is attacked by the piece on attacker
+    AttackedSquare { square: Square, attacker: Square },
 }
 
 use std::error;
@@ -778,25 +592,133 @@ impl fmt::Display for MoveError {
             MoveError::OpponentPiece => write!(f, "Opponent piece: the piece you are trying to move is not yours"),
             MoveError::EmptySquare => write!(f, "Empty square: the square you are trying to move from is empty"),
             MoveError::WrongPieceMovement => write!(f, "Wrong piece movement: the piece you are trying to move cant move like that"),
-            MoveError::Collision => write!(f, "Collision: the piece you are trying to move cant move there because of a collision. Either with a piece of your own or the opponents"),
+            MoveError::Collision { at } => write!(f, "Collision: the piece you are trying to move cant move there because the piece on {} is in the way", at),
             MoveError::PawnDubbleMove => write!(f, "Pawn dubble move: the pawn you are trying to move has already moved. Can only move two squares on the first move"),
-            MoveError::CastlingError => write!(f, "Castling error: the castling you are trying to do is not possible"),
-            MoveError::SelfCheck => write!(f, "Self check: the move you are trying to do leads to check for yourself"),
-            MoveError::None => write!(f, "None: the move you are trying to do is not possible"),
+            MoveError::SelfCheck { attacker } => write!(f, "Self check: the move you are trying to do leaves your king in check by the piece on {}", attacker),
+            MoveError::Pinned { by } => write!(f, "Pinned: the piece you are trying to move is pinned to your king by the piece on {}", by),
+            MoveError::CastlingError { reason } => write!(f, "Castling error: {}", reason),
+            MoveError::PromotionPending => write!(f, "Promotion pending: a pawn has reached the last rank and has to be promoted before the next move"),
+            MoveError::NotPromoting => write!(f, "Not promoting: there is no pawn waiting to be promoted"),
+            MoveError::InvalidPromotion => write!(f, "Invalid promotion: a pawn can only be promoted to a queen, rook, bishop or knight"),
+            MoveError::Timeout => write!(f, "Timeout: the game is over because a player ran out of time"),
+            MoveError::GameOver => write!(f, "Game over: the game has already ended"),
+            MoveError::NoDrawOffer => write!(f, "No draw offer: the opponent has not offered a draw"),
+        }
+    }
+}
+
+impl fmt::Display for CastlingBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CastlingBlock::NoRight => write!(f, "the king or the rook has already moved"),
+            CastlingBlock::InCheck { attacker } => write!(f, "the king is in check by the piece on {}", attacker),
+            CastlingBlock::AttackedSquare { square, attacker } => write!(
+                f, "the king can not pass {} which is attacked by the piece on {}", square, attacker
+            ),
         }
     }
 }
 
 impl error::Error for MoveError { }
 
+///Written as the FEN letter of the piece, uppercase for white and lowercase for black. Pawns are written the same whether they have moved or not.
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", fen::piece_to_fen(*self))
+    }
+}
+
+/**
+Parses a FEN letter. Whether a pawn has moved is not part of the text form, so Pawn(true) and Pawn(false) are both written "P" and
+a parsed pawn is always Pawn(false). The text form is lossless for the color and the piece type only.
+*/
+impl std::str::FromStr for Piece {
+    type Err = util::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return Err(util::ParseError::InvalidPiece);
+        };
+        let piece = match c.to_ascii_lowercase() {
+            'p' => PieceTypes::Pawn(false),
+            'n' => PieceTypes::Knight,
+            'b' => PieceTypes::Bishop,
+            'r' => PieceTypes::Rook,
+            'q' => PieceTypes::Queen,
+            'k' => PieceTypes::King,
+            _ => return Err(util::ParseError::InvalidPiece),
+        };
+        let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        Ok(Piece { piece, color })
+    }
+}
+
+/**
+Written in UCI with the from and to squares, e.g. "e2e4", and the promotion piece added for promotions, e.g. "e7e8q".
+A castle is written as the king move of standard chess, e.g. "e1g1" or "e8c8". Game::to_uci writes Chess960 castles as the king taking its rook instead.
+*/
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Move::Normal { from, to } => write!(f, "{}{}", from, to),
+            Move::Promotion { from, to, piece } => {
+                let letter = match piece {
+                    PieceTypes::Rook => 'r',
+                    PieceTypes::Bishop => 'b',
+                    PieceTypes::Knight => 'n',
+                    _ => 'q',
+                };
+                write!(f, "{}{}{}", from, to, letter)
+            },
+            Move::Castle { side, color } => {
+                let rank = match color {
+                    Color::White => Rank::R1,
+                    Color::Black => Rank::R8,
+                };
+                let to = match side {
+                    CastlingSide::KingSide => File::G,
+                    CastlingSide::QueenSide => File::C,
+                };
+                write!(f, "{}{}", Square { file: File::E, rank }, Square { file: to, rank })
+            },
+        }
+    }
+}
+
+/**
+Parses a move in UCI like "e2e4" or "e7e8q". A rook or queen can make the same move as a castling king, so "e1g1" is parsed as a Normal move.
+try_move plays it as the castle when the king stands on e1 and can castle, and Game::parse_uci gives the castle of the position directly.
+*/
+impl std::str::FromStr for Move {
+    type Err = util::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !(4..=5).contains(&s.len()) || !s.is_ascii() {
+            return Err(util::ParseError::InvalidMove);
+        }
+
+        let from = s[0..2].parse().map_err(|_| util::ParseError::InvalidMove)?;
+        let to = s[2..4].parse().map_err(|_| util::ParseError::InvalidMove)?;
+        let piece = match &s[4..] {
+            "" => return Ok(Move::Normal { from, to }),
+            "q" => PieceTypes::Queen,
+            "r" => PieceTypes::Rook,
+            "b" => PieceTypes::Bishop,
+            "n" => PieceTypes::Knight,
+            _ => return Err(util::ParseError::InvalidMove),
+        };
+        Ok(Move::Promotion { from, to, piece })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use pgn_reader::{Visitor, Skip, BufferedReader, SanPlus, Outcome};
     use super::*;
+    use crate::test_util::sq;
     use shakmaty::{Chess, Position};
 
-    use reqwest;
-    use tokio;
     use std::env;
     use shakmaty::Outcome::Decisive;
 
@@ -906,6 +828,20 @@ mod tests {
                 .unwrap();
 
             match mov {
+                shakmaty::Move::Normal { from, to, promotion: Some(role), .. } => {
+                    self.moves.push(
+                        crate::Move::Promotion {
+                            from: square_to_square(from),
+                            to: square_to_square(to),
+                            piece: match role {
+                                shakmaty::Role::Queen => PieceTypes::Queen,
+                                shakmaty::Role::Rook => PieceTypes::Rook,
+                                shakmaty::Role::Bishop => PieceTypes::Bishop,
+                                _ => PieceTypes::Knight,
+                            },
+                        }
+                    );
+                },
                 shakmaty::Move::Normal { from, to, .. } => {
                     self.moves.push(
                         crate::Move::Normal {
@@ -914,14 +850,23 @@ mod tests {
                         }
                     );
                 },
-                shakmaty::Move::Castle { king, rook } => {
-                    self.moves.push(crate::Move::Castle {
-                        side: match rook {
-                            shakmaty::Square::A1 | shakmaty::Square::A8 => CastlingSide::QueenSide,
-                            shakmaty::Square::H1 | shakmaty::Square::H8 => CastlingSide::KingSide,
-                            _ => unreachable!(),
+                shakmaty::Move::EnPassant { from, to } => {
+                    self.moves.push(
+                        crate::Move::Normal {
+                            from: square_to_square(from),
+                            to: square_to_square(to)
                         }
-                    });
+                    );
+                },
+                shakmaty::Move::Castle { rook, .. } => {
+                    let (side, color) = match rook {
+                        shakmaty::Square::A1 => (CastlingSide::QueenSide, Color::White),
+                        shakmaty::Square::H1 => (CastlingSide::KingSide, Color::White),
+                        shakmaty::Square::A8 => (CastlingSide::QueenSide, Color::Black),
+                        shakmaty::Square::H8 => (CastlingSide::KingSide, Color::Black),
+                        _ => unreachable!(),
+                    };
+                    self.moves.push(crate::Move::Castle { side, color });
                 }
                 _ => {},
             }
@@ -938,7 +883,7 @@ mod tests {
         }
 
         fn end_game(&mut self) -> Self::Result {
-            (self.moves.clone(), self.outcome.clone())
+            (self.moves.clone(), self.outcome)
         }
     }
 
@@ -967,19 +912,257 @@ mod tests {
         }
     }
 
-    fn piece_to_char(piece: Option<Piece>) -> char {
-        match piece {
-            Some(Piece { piece: PieceTypes::Bishop, .. }) => 'b',
-            Some(Piece { piece: PieceTypes::Rook, .. }) => 'r',
-            Some(Piece { piece: PieceTypes::King, .. }) => 'K',
-            Some(Piece { piece: PieceTypes::Queen, .. }) => 'q',
-            Some(Piece { piece: PieceTypes::Knight, .. }) => 'k',
-            Some(Piece { piece: PieceTypes::Pawn(_), .. }) => 'p',
-            None => '.',
+    ///a game with only the two kings on the board and no castling rights.
+    fn kings_only(white_king: Square, black_king: Square) -> Game {
+        let mut game = Game::new();
+        game.board = Board::from([[None; 8]; 8]);
+        game.board.set(white_king, Some(Piece { piece: PieceTypes::King, color: Color::White }));
+        game.board.set(black_king, Some(Piece { piece: PieceTypes::King, color: Color::Black }));
+        game.castling = Castling { white: (None, None), black: (None, None) };
+        game
+    }
+
+    #[test]
+    fn promotion_handshake() {
+        let mut game = kings_only(Square { file: File::E, rank: Rank::R1 }, Square { file: File::H, rank: Rank::R5 });
+        let from = Square { file: File::B, rank: Rank::R7 };
+        let to = Square { file: File::B, rank: Rank::R8 };
+        game.board.set(from, Some(Piece { piece: PieceTypes::Pawn(true), color: Color::White }));
+
+        assert_eq!(game.promote(PieceTypes::Queen), Err(MoveError::NotPromoting));
+
+        game.try_move(Move::Normal { from, to }).unwrap();
+        assert_eq!(game.game_status, GameStatus::Promoting);
+        assert_eq!(game.turn, Color::White);
+
+        let king_move = Move::Normal {
+            from: Square { file: File::E, rank: Rank::R1 },
+            to: Square { file: File::E, rank: Rank::R2 },
+        };
+        assert_eq!(game.try_move(king_move), Err(MoveError::PromotionPending));
+        assert_eq!(game.promote(PieceTypes::King), Err(MoveError::InvalidPromotion));
+
+        game.promote(PieceTypes::Rook).unwrap();
+        assert_eq!(game.game_status, GameStatus::Ongoing);
+        assert_eq!(game.turn, Color::Black);
+        assert_eq!(game.board[to], Some(Piece { piece: PieceTypes::Rook, color: Color::White }));
+    }
+
+    #[test]
+    fn promotion_move_gives_check() {
+        let mut game = kings_only(Square { file: File::E, rank: Rank::R1 }, Square { file: File::H, rank: Rank::R8 });
+        let from = Square { file: File::A, rank: Rank::R7 };
+        let to = Square { file: File::A, rank: Rank::R8 };
+        game.board.set(from, Some(Piece { piece: PieceTypes::Pawn(true), color: Color::White }));
+
+        assert_eq!(
+            game.try_move(Move::Promotion { from, to: Square { file: File::A, rank: Rank::R6 }, piece: PieceTypes::Queen }),
+            Err(MoveError::WrongPieceMovement)
+        );
+
+        game.try_move(Move::Promotion { from, to, piece: PieceTypes::Queen }).unwrap();
+        assert_eq!(game.board[to], Some(Piece { piece: PieceTypes::Queen, color: Color::White }));
+        assert_eq!(game.game_status, GameStatus::Ongoing);
+        assert_eq!(game.turn, Color::Black);
+        assert!(game.check);
+    }
+
+    #[test]
+    fn en_passant_capture() {
+        let mut game = Game::new();
+
+        for (from, to) in [
+            (sq(File::E, Rank::R2), sq(File::E, Rank::R4)),
+            (sq(File::A, Rank::R7), sq(File::A, Rank::R6)),
+            (sq(File::E, Rank::R4), sq(File::E, Rank::R5)),
+            (sq(File::D, Rank::R7), sq(File::D, Rank::R5)),
+        ] {
+            game.try_move(Move::Normal { from, to }).unwrap();
         }
+        assert_eq!(game.en_passant, Some(sq(File::D, Rank::R6)));
+
+        let (moves, _) = game.possible_moves(sq(File::E, Rank::R5)).unwrap();
+        assert!(moves[sq(File::D, Rank::R6)].is_some());
+
+        game.try_move(Move::Normal { from: sq(File::E, Rank::R5), to: sq(File::D, Rank::R6) }).unwrap();
+        assert_eq!(game.board[sq(File::D, Rank::R5)], None);
+        assert_eq!(game.captured, vec![Piece { piece: PieceTypes::Pawn(true), color: Color::Black }]);
+        assert_eq!(game.en_passant, None);
+    }
+
+    #[test]
+    fn en_passant_only_right_after_double_move() {
+        let mut game = Game::new();
+
+        for (from, to) in [
+            (sq(File::E, Rank::R2), sq(File::E, Rank::R4)),
+            (sq(File::A, Rank::R7), sq(File::A, Rank::R6)),
+            (sq(File::E, Rank::R4), sq(File::E, Rank::R5)),
+            (sq(File::D, Rank::R7), sq(File::D, Rank::R5)),
+            (sq(File::A, Rank::R2), sq(File::A, Rank::R3)),
+            (sq(File::A, Rank::R6), sq(File::A, Rank::R5)),
+        ] {
+            game.try_move(Move::Normal { from, to }).unwrap();
+        }
+
+        assert_eq!(
+            game.try_move(Move::Normal { from: sq(File::E, Rank::R5), to: sq(File::D, Rank::R6) }),
+            Err(MoveError::WrongPieceMovement)
+        );
+    }
+
+    #[test]
+    fn en_passant_self_check() {
+        let mut game = kings_only(sq(File::A, Rank::R5), sq(File::H, Rank::R8));
+        game.board.set(sq(File::D, Rank::R5), Some(Piece { piece: PieceTypes::Pawn(true), color: Color::White }));
+        game.board.set(sq(File::E, Rank::R7), Some(Piece { piece: PieceTypes::Pawn(false), color: Color::Black }));
+        game.board.set(sq(File::H, Rank::R5), Some(Piece { piece: PieceTypes::Rook, color: Color::Black }));
+        game.turn = Color::Black;
+
+        game.try_move(Move::Normal { from: sq(File::E, Rank::R7), to: sq(File::E, Rank::R5) }).unwrap();
+        assert_eq!(
+            game.try_move(Move::Normal { from: sq(File::D, Rank::R5), to: sq(File::E, Rank::R6) }),
+            Err(MoveError::Pinned { by: sq(File::H, Rank::R5) })
+        );
+    }
+
+    #[test]
+    fn stalemate() {
+        let game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.game_status, GameStatus::Draw(DrawReason::Stalemate));
+
+        let mut game = Game::from_fen("7k/8/6K1/8/8/8/5Q2/8 w - - 0 1").unwrap();
+        let mv = game.parse_san("Qf7").unwrap();
+        game.try_move(mv).unwrap();
+        assert_eq!(game.game_status, GameStatus::Draw(DrawReason::Stalemate));
+        assert!(!game.check);
+    }
+
+    #[test]
+    fn insufficient_material() {
+        for fen in [
+            "8/8/4k3/8/8/4K3/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3BK3/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3NK3/8/8 b - - 0 1",
+            "8/8/2b1k3/8/8/3BK3/8/8 w - - 0 1",
+        ] {
+            assert_eq!(Game::from_fen(fen).unwrap().game_status, GameStatus::Draw(DrawReason::InsufficientMaterial), "{}", fen);
+        }
+        for fen in [
+            "8/8/3bk3/8/8/3BK3/8/8 w - - 0 1",
+            "8/8/4k3/8/8/2NNK3/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3PK3/8/8 w - - 0 1",
+        ] {
+            assert_eq!(Game::from_fen(fen).unwrap().game_status, GameStatus::Ongoing, "{}", fen);
+        }
+
+        let mut game = Game::from_fen("8/8/8/8/4k3/3R4/8/7K b - - 0 1").unwrap();
+        assert_eq!(game.game_status, GameStatus::Ongoing);
+        game.try_move(game.parse_san("Kxd3").unwrap()).unwrap();
+        assert_eq!(game.game_status, GameStatus::Draw(DrawReason::InsufficientMaterial));
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut game = Game::new();
+        for san in ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"] {
+            game.try_move(game.parse_san(san).unwrap()).unwrap();
+            assert_eq!(game.game_status, GameStatus::Ongoing);
+        }
+        game.try_move(game.parse_san("Ng8").unwrap()).unwrap();
+        assert_eq!(game.game_status, GameStatus::Draw(DrawReason::ThreefoldRepetition));
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/R7/4K3 w - - 99 80").unwrap();
+        assert_eq!(game.game_status, GameStatus::Ongoing);
+        game.try_move(game.parse_san("Ra3").unwrap()).unwrap();
+        assert_eq!(game.game_status, GameStatus::Draw(DrawReason::FiftyMoveRule));
+
+        let mut game = Game::from_fen("4k3/R7/8/8/8/8/8/1R2K3 w - - 99 80").unwrap();
+        game.try_move(game.parse_san("Rb8").unwrap()).unwrap();
+        assert_eq!(game.game_status, GameStatus::Checkmate(Color::White));
+    }
+
+    #[test]
+    fn queries_through_shared_reference() {
+        use std::sync::{Arc, RwLock};
+
+        let game = Arc::new(RwLock::new(Game::new()));
+        let from = Square { file: File::G, rank: Rank::R1 };
+
+        let handles: Vec<_> = (0..4).map(|_| {
+            let game = Arc::clone(&game);
+            std::thread::spawn(move || {
+                let game = game.read().unwrap();
+                let (moves, _) = game.possible_moves(from).unwrap();
+                (game.legal_moves().len(), get_square_array().into_iter().filter(|s| moves[*s].is_some()).count(), game.in_check(game.turn))
+            })
+        }).collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), (20, 2, false));
+        }
+        assert_eq!(game.read().unwrap().to_fen(), fen::STARTING_FEN);
+    }
+
+    #[test]
+    fn text_forms() {
+        use util::ParseError;
+
+        let e4 = Square { file: File::E, rank: Rank::R4 };
+        assert_eq!(e4.to_string(), "e4");
+        assert_eq!("e4".parse(), Ok(e4));
+        assert_eq!("h".parse(), Ok(File::H));
+        assert_eq!("8".parse(), Ok(Rank::R8));
+        assert_eq!("i4".parse::<Square>(), Err(ParseError::InvalidSquare));
+        assert_eq!("e9".parse::<Square>(), Err(ParseError::InvalidSquare));
+        assert_eq!("e44".parse::<Square>(), Err(ParseError::InvalidSquare));
+        assert_eq!("".parse::<File>(), Err(ParseError::InvalidFile));
+        assert_eq!("0".parse::<Rank>(), Err(ParseError::InvalidRank));
+
+        let knight = Piece { piece: PieceTypes::Knight, color: Color::Black };
+        assert_eq!(knight.to_string(), "n");
+        assert_eq!("n".parse(), Ok(knight));
+        assert_eq!("P".parse(), Ok(Piece { piece: PieceTypes::Pawn(false), color: Color::White }));
+        assert_eq!("x".parse::<Piece>(), Err(ParseError::InvalidPiece));
+
+        //the text form has no room for whether a pawn has moved
+        assert_eq!(Piece { piece: PieceTypes::Pawn(true), color: Color::White }.to_string(), "P");
+
+        for text in ["e2e4", "e7e8q", "b7a8n", "g2g1r"] {
+            assert_eq!(text.parse::<Move>().unwrap().to_string(), text);
+        }
+        for text in ["e2e", "e2e4k", "e2e4qq", "O-O", "e2-e4", "i2i4"] {
+            assert_eq!(text.parse::<Move>(), Err(ParseError::InvalidMove), "{}", text);
+        }
+        assert_eq!(Move::Castle { side: CastlingSide::KingSide, color: Color::White }.to_string(), "e1g1");
+        assert_eq!(Move::Castle { side: CastlingSide::QueenSide, color: Color::Black }.to_string(), "e8c8");
+    }
+
+    #[test]
+    fn castle_written_as_king_move() {
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        game.try_move("e1g1".parse().unwrap()).unwrap();
+        game.try_move("e8c8".parse().unwrap()).unwrap();
+        assert_eq!(game.moves, [Move::Castle { side: CastlingSide::KingSide, color: Color::White }, Move::Castle { side: CastlingSide::QueenSide, color: Color::Black }]);
+        assert_eq!(game.to_fen(), "2kr3r/8/8/8/8/8/8/R4RK1 w - - 2 2");
+
+        //the text of a castle is played as the same castle again
+        let mut replayed = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        for mv in &game.moves {
+            replayed.try_move(mv.to_string().parse().unwrap()).unwrap();
+        }
+        assert_eq!(replayed.moves, game.moves);
+
+        assert!(game.try_move("a1a3".parse().unwrap()).is_ok());
+        assert_eq!(game.try_move("e8g8".parse().unwrap()), Err(MoveError::EmptySquare));
+        assert_eq!(game.try_move(Move::Castle { side: CastlingSide::KingSide, color: Color::White }), Err(MoveError::OpponentPiece));
     }
 
     #[tokio::test]
+    #[ignore = "downloads games from the lichess database"]
     async fn database_games_test() {
         env::set_var("RUST_BACKTRACE", "1");
 
@@ -994,13 +1177,10 @@ mod tests {
             println!("{}", res);
 
 
-            let mut reader = BufferedReader::new_cursor(&pgn[..]);
+            let mut reader = BufferedReader::new_cursor(pgn);
 
             let mut test_game = TestGame::new();
-            let (moves, outcome) = match reader.read_game(&mut test_game).unwrap() {
-                Some(m) => m,
-                None => (vec![], None),
-            };
+            let (moves, outcome) = reader.read_game(&mut test_game).unwrap().unwrap_or_default();
 
             let mut game = Game::new();
             println!("{:?}", moves);
@@ -1008,10 +1188,10 @@ mod tests {
 
             for mv in moves {
                 match mv {
-                    Move::Normal { from, to } => {
-                        println!("{:?} is moving {}, from {:?}, {:?} to {:?}, {:?}", game.turn, piece_to_char(game.board[from]), from.file, from.rank, to.file, to.rank);
+                    Move::Normal { from, to } | Move::Promotion { from, to, .. } => {
+                        println!("{:?} is moving {}, from {:?}, {:?} to {:?}, {:?}", game.turn, game.board[from].map_or('.', fen::piece_to_fen), from.file, from.rank, to.file, to.rank);
                     },
-                    Move::Castle { side } => {
+                    Move::Castle { side, .. } => {
                         println!("{:?} is castling {}", game.turn, match side {
                             CastlingSide::KingSide => "king side",
                             CastlingSide::QueenSide => "queen side",
@@ -1021,17 +1201,7 @@ mod tests {
 
                 game.try_move(mv).unwrap();
 
-                println!("  A  B  C  D  E  F  G  H ");
-                let mut y_cord = 8;
-                for y in RANK_ARRAY.iter().rev() {
-                    let mut t = String::new();
-
-                    for x in FILE_ARRAY {
-                        t += format!("[{}]", piece_to_char(game.board[y.clone()][x])).as_str();
-                    }
-                    println!("{y_cord}{}",t);
-                    y_cord -= 1;
-                }
+                println!("{}", game.board);
             }
             match outcome {
                 Some(Decisive { winner: pgn_reader::Color::White }) => {
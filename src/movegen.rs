@@ -0,0 +1,415 @@
+use crate::{CastlingBlock, CastlingSide, Color, Game, Move, MoveError, Piece, PieceTypes};
+use crate::bitboard::{self, Bitboard, KING_ATTACKS, KNIGHT_ATTACKS, PAWN_ATTACKS, bishop_attacks, rook_attacks};
+use crate::util::{Board, File, Rank, Square};
+
+const PROMOTION_PIECES: [PieceTypes; 4] = [PieceTypes::Queen, PieceTypes::Rook, PieceTypes::Bishop, PieceTypes::Knight];
+
+///the square offset from square or None if it is outside the board
+fn offset(square: Square, (file, rank): (i32, i32)) -> Option<Square> {
+    Square::try_from((square.file.num() + file, square.rank.num() + rank)).ok()
+}
+
+///the rank direction pawns of color move in
+fn pawn_direction(color: Color) -> i32 {
+    match color {
+        Color::White => 1,
+        Color::Black => -1,
+    }
+}
+
+///the rank the pawns of color start on and the rank they promote on
+fn pawn_rows(color: Color) -> (Rank, Rank) {
+    match color {
+        Color::White => (Rank::R2, Rank::R8),
+        Color::Black => (Rank::R7, Rank::R1),
+    }
+}
+
+///the squares from the king to the square it castles to, both included
+fn king_path(king: Square, king_to: Square) -> impl Iterator<Item = Square> {
+    let (low, high) = (king.file.num().min(king_to.file.num()), king.file.num().max(king_to.file.num()));
+    (low..=high).map(move |file| Square { file: File::try_from(file).unwrap(), rank: king.rank })
+}
+
+///the files the king and the rook end up on after castling on side, which are the same in Chess960
+pub(crate) fn castling_targets(side: CastlingSide) -> (File, File) {
+    match side {
+        CastlingSide::KingSide => (File::G, File::F),
+        CastlingSide::QueenSide => (File::C, File::D),
+    }
+}
+
+pub(crate) fn home_row(color: Color) -> Rank {
+    match color {
+        Color::White => Rank::R1,
+        Color::Black => Rank::R8,
+    }
+}
+
+///checks if square is attacked by a piece of the color by
+pub(crate) fn attacked(board: &Board, square: Square, by: Color) -> bool {
+    attackers(board, square, by) != 0
+}
+
+///the pieces of the color by that attack square
+pub(crate) fn attackers(board: &Board, square: Square, by: Color) -> Bitboard {
+    let index = bitboard::index(square);
+    let occupied = board.occupied();
+    let queens = board.pieces(PieceTypes::Queen, by);
+
+    KNIGHT_ATTACKS[index] & board.pieces(PieceTypes::Knight, by)
+        | KING_ATTACKS[index] & board.pieces(PieceTypes::King, by)
+        | PAWN_ATTACKS[bitboard::color_index(by.opposite())][index] & board.pieces(PieceTypes::Pawn(false), by)
+        | rook_attacks(index, occupied) & (board.pieces(PieceTypes::Rook, by) | queens)
+        | bishop_attacks(index, occupied) & (board.pieces(PieceTypes::Bishop, by) | queens)
+}
+
+///the first attacker of square, used to point out the piece that makes a move illegal
+fn attacker(board: &Board, square: Square, by: Color) -> Option<Square> {
+    bitboard::squares(attackers(board, square, by)).next()
+}
+
+///the square of the king of color
+pub(crate) fn king_square(board: &Board, color: Color) -> Option<Square> {
+    bitboard::squares(board.pieces(PieceTypes::King, color)).next()
+}
+
+impl Game {
+    /// Returns every legal move of the player in turn. Pawn moves to the last rank are returned as one Move::Promotion per promotion piece.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = self.pseudo_legal_moves();
+        moves.retain(|mv| !self.leaves_king_in_check(*mv));
+        moves
+    }
+
+    /// Checks if the king of color is attacked on the current board.
+    pub fn in_check(&self, color: Color) -> bool {
+        king_square(&self.board, color).is_some_and(|king| attacked(&self.board, king, color.opposite()))
+    }
+
+    ///moves that follow the movement rules of the pieces but may leave the own king in check. Castles are only included when they are legal.
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        for from in bitboard::squares(self.board.color(self.turn)) {
+            if let Some(piece) = self.board[from] {
+                self.piece_moves(from, piece, &mut moves);
+            }
+        }
+        self.castling_moves(&mut moves);
+        moves
+    }
+
+    fn piece_moves(&self, from: Square, piece: Piece, moves: &mut Vec<Move>) {
+        let index = bitboard::index(from);
+        let occupied = self.board.occupied();
+        let targets = match piece.piece {
+            PieceTypes::Knight => KNIGHT_ATTACKS[index],
+            PieceTypes::King => KING_ATTACKS[index],
+            PieceTypes::Bishop => bishop_attacks(index, occupied),
+            PieceTypes::Rook => rook_attacks(index, occupied),
+            PieceTypes::Queen => bishop_attacks(index, occupied) | rook_attacks(index, occupied),
+            PieceTypes::Pawn(_) => return self.pawn_moves(from, piece.color, moves),
+        };
+
+        let targets: Bitboard = targets & !self.board.color(piece.color);
+        moves.extend(bitboard::squares(targets).map(|to| Move::Normal { from, to }));
+    }
+
+    fn pawn_moves(&self, from: Square, color: Color, moves: &mut Vec<Move>) {
+        let direction = pawn_direction(color);
+        let (start_row, last_row) = pawn_rows(color);
+
+        let push = |moves: &mut Vec<Move>, to: Square| {
+            if to.rank == last_row {
+                moves.extend(PROMOTION_PIECES.iter().map(|piece| Move::Promotion { from, to, piece: *piece }));
+            }
+            else { moves.push(Move::Normal { from, to }); }
+        };
+
+        //checks one or two moves forward
+        if let Some(one) = offset(from, (0, direction)).filter(|s| self.board[*s].is_none()) {
+            push(moves, one);
+
+            if from.rank == start_row {
+                if let Some(two) = offset(from, (0, direction * 2)).filter(|s| self.board[*s].is_none()) {
+                    moves.push(Move::Normal { from, to: two });
+                }
+            }
+        }
+
+        //checks for enemies and en passant in the diagonals
+        let attacks = PAWN_ATTACKS[bitboard::color_index(color)][bitboard::index(from)];
+        for to in bitboard::squares(attacks & self.board.color(color.opposite())) {
+            push(moves, to);
+        }
+        if let Some(to) = self.en_passant.filter(|s| attacks & bitboard::bit(*s) != 0) {
+            if self.en_passant_capture(from, to).is_some() {
+                moves.push(Move::Normal { from, to });
+            }
+        }
+    }
+
+    fn castling_moves(&self, moves: &mut Vec<Move>) {
+        if self.in_check(self.turn) {
+            return;
+        }
+
+        for side in [CastlingSide::KingSide, CastlingSide::QueenSide] {
+            let Some((king, rook, king_to, _)) = self.castling_squares(side) else { continue };
+
+            //the squares the king passes over and ends on can't be attacked
+            let safe = king_path(king, king_to).all(|square| !attacked(&self.board, square, self.turn.opposite()));
+
+            if safe && self.castling_blocker(side, king, rook).is_none() {
+                moves.push(Move::Castle { side, color: self.turn });
+            }
+        }
+    }
+
+    /**
+    The squares of the king and the rook and the squares they move to for the castle of the player in turn on side. Returns None if the player
+    does not have the right to castle on that side or the king and rook are not on their squares. Works for both standard chess and Chess960.
+    */
+    pub(crate) fn castling_squares(&self, side: CastlingSide) -> Option<(Square, Square, Square, Square)> {
+        let home_row = home_row(self.turn);
+        let rook = Square { file: self.castling.rook(self.turn, side)?, rank: home_row };
+        let king = king_square(&self.board, self.turn).filter(|king| king.rank == home_row)?;
+
+        if self.board[rook] != Some(Piece { piece: PieceTypes::Rook, color: self.turn }) {
+            return None;
+        }
+
+        let (king_to, rook_to) = castling_targets(side);
+        Some((king, rook, Square { file: king_to, rank: home_row }, Square { file: rook_to, rank: home_row }))
+    }
+
+    ///the first piece on a square the king or rook pass over or move to, apart from the king and rook themselves
+    fn castling_blocker(&self, side: CastlingSide, king: Square, rook: Square) -> Option<Square> {
+        let (king_to, rook_to) = castling_targets(side);
+        let files = [king.file, rook.file, king_to, rook_to];
+        let low = files.iter().map(|f| f.num()).min().unwrap_or(0);
+        let high = files.iter().map(|f| f.num()).max().unwrap_or(0);
+
+        (low..=high)
+            .map(|file| Square { file: File::try_from(file).unwrap(), rank: king.rank })
+            .find(|square| *square != king && *square != rook && self.board[*square].is_some())
+    }
+
+    ///the board after the move is made by the player in turn. Does not check if the move is legal.
+    pub(crate) fn board_after(&self, mv: Move) -> Board {
+        let mut board = self.board;
+
+        match mv {
+            Move::Castle { side, .. } => {
+                //the king and rook are both lifted before they are placed since their squares can overlap in Chess960
+                if let Some((king, rook, king_to, rook_to)) = self.castling_squares(side) {
+                    let (king_piece, rook_piece) = (board[king], board[rook]);
+                    board.set(king, None);
+                    board.set(rook, None);
+                    board.set(king_to, king_piece);
+                    board.set(rook_to, rook_piece);
+                }
+            },
+            Move::Normal { from, to } | Move::Promotion { from, to, .. } => {
+                if let Some(square) = self.en_passant_capture(from, to) {
+                    board.set(square, None);
+                }
+
+                let moved = match (mv, board[from]) {
+                    (Move::Promotion { piece, .. }, Some(pawn)) => Some(Piece { piece, color: pawn.color }),
+                    (_, Some(Piece { piece: PieceTypes::Pawn(_), color })) => Some(Piece { piece: PieceTypes::Pawn(true), color }),
+                    (_, piece) => piece,
+                };
+                board.set(to, moved);
+                board.set(from, None);
+            },
+        }
+
+        board
+    }
+
+    fn leaves_king_in_check(&self, mv: Move) -> bool {
+        let board = self.board_after(mv);
+        king_square(&board, self.turn).is_some_and(|king| attacked(&board, king, self.turn.opposite()))
+    }
+
+    ///explains why a move that is not in legal_moves is illegal
+    pub(crate) fn move_error(&self, mv: Move) -> MoveError {
+        let (from, to) = match mv {
+            Move::Castle { color, .. } if color != self.turn => return MoveError::OpponentPiece,
+            Move::Castle { side, .. } => return self.castling_error(side),
+            Move::Normal { from, to } | Move::Promotion { from, to, .. } => (from, to),
+        };
+
+        let piece = match self.board[from] {
+            Some(piece) if piece.color != self.turn => return MoveError::OpponentPiece,
+            Some(piece) => piece,
+            None => return MoveError::EmptySquare,
+        };
+
+        if matches!(mv, Move::Promotion { .. }) && (!matches!(piece.piece, PieceTypes::Pawn(_)) || to.rank != pawn_rows(piece.color).1) {
+            return MoveError::WrongPieceMovement;
+        }
+
+        if matches!(self.board[to], Some(p) if p.color == piece.color) {
+            return MoveError::Collision { at: to };
+        }
+
+        let mut pseudo_legal = vec![];
+        self.piece_moves(from, piece, &mut pseudo_legal);
+        if pseudo_legal.iter().any(|m| matches!(m, Move::Normal { to: t, .. } | Move::Promotion { to: t, .. } if *t == to)) {
+            return self.check_error(mv, piece);
+        }
+
+        let file_diff = to.file.num() - from.file.num();
+        let rank_diff = to.rank.num() - from.rank.num();
+        let collision = MoveError::Collision { at: self.first_blocker(from, to) };
+
+        match piece.piece {
+            PieceTypes::Pawn(_) => {
+                let forward = rank_diff * pawn_direction(piece.color);
+                match (file_diff, forward) {
+                    (0, 2) if from.rank != pawn_rows(piece.color).0 => MoveError::PawnDubbleMove,
+                    (0, 1) | (0, 2) => collision,
+                    _ => MoveError::WrongPieceMovement,
+                }
+            },
+            PieceTypes::Bishop if file_diff.abs() == rank_diff.abs() => collision,
+            PieceTypes::Rook if file_diff == 0 || rank_diff == 0 => collision,
+            PieceTypes::Queen if file_diff.abs() == rank_diff.abs() || file_diff == 0 || rank_diff == 0 => collision,
+            _ => MoveError::WrongPieceMovement,
+        }
+    }
+
+    ///the first occupied square on the line from from to to, or to itself if the line is free
+    fn first_blocker(&self, from: Square, to: Square) -> Square {
+        let step = ((to.file.num() - from.file.num()).signum(), (to.rank.num() - from.rank.num()).signum());
+        let mut square = from;
+        while let Some(next) = offset(square, step).filter(|s| *s != to) {
+            if self.board[next].is_some() {
+                return next;
+            }
+            square = next;
+        }
+        to
+    }
+
+    ///the error of a move the piece can make that leaves the own king in check. A piece that uncovers a new attack on the king is pinned.
+    fn check_error(&self, mv: Move, piece: Piece) -> MoveError {
+        let opponent = self.turn.opposite();
+        let board = self.board_after(mv);
+        let Some(king) = king_square(&board, self.turn) else { return MoveError::WrongPieceMovement };
+
+        let before = king_square(&self.board, self.turn).map_or(0, |king| attackers(&self.board, king, opponent));
+        let after = attackers(&board, king, opponent);
+
+        match bitboard::squares(after & !before).next() {
+            Some(by) if piece.piece != PieceTypes::King => MoveError::Pinned { by },
+            _ => MoveError::SelfCheck { attacker: bitboard::squares(after).next().unwrap_or(king) },
+        }
+    }
+
+    ///explains why the player in turn can't castle on side
+    fn castling_error(&self, side: CastlingSide) -> MoveError {
+        let Some((king, rook, king_to, _)) = self.castling_squares(side) else {
+            return MoveError::CastlingError { reason: CastlingBlock::NoRight };
+        };
+        if let Some(at) = self.castling_blocker(side, king, rook) {
+            return MoveError::Collision { at };
+        }
+
+        let opponent = self.turn.opposite();
+        if let Some(attacker) = attacker(&self.board, king, opponent) {
+            return MoveError::CastlingError { reason: CastlingBlock::InCheck { attacker } };
+        }
+        for square in king_path(king, king_to) {
+            if let Some(attacker) = attacker(&self.board, square, opponent) {
+                return MoveError::CastlingError { reason: CastlingBlock::AttackedSquare { square, attacker } };
+            }
+        }
+
+        //in Chess960 the rook can uncover an attack on the square the king castles to
+        self.check_error(Move::Castle { side, color: self.turn }, Piece { piece: PieceTypes::King, color: self.turn })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sq;
+
+    #[test]
+    fn castling_moves() {
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let moves = game.legal_moves();
+        assert!(moves.contains(&Move::Castle { side: CastlingSide::KingSide, color: Color::White }));
+        assert!(moves.contains(&Move::Castle { side: CastlingSide::QueenSide, color: Color::White }));
+
+        //the f1 square is attacked so white can't castle king side
+        let game = Game::from_fen("r3kr2/8/8/8/8/8/8/R3K2R w KQq - 0 1").unwrap();
+        let moves = game.legal_moves();
+        assert!(!moves.contains(&Move::Castle { side: CastlingSide::KingSide, color: Color::White }));
+        assert!(moves.contains(&Move::Castle { side: CastlingSide::QueenSide, color: Color::White }));
+    }
+
+    #[test]
+    fn sliders_stop_at_pieces() {
+        let game = Game::from_fen("4k3/8/8/8/3p4/8/1R1P4/4K3 w - - 0 1").unwrap();
+        let (moves, _) = game.possible_moves(Square { file: File::B, rank: Rank::R2 }).unwrap();
+        assert!(moves[Square { file: File::C, rank: Rank::R2 }].is_some());
+        assert!(moves[Square { file: File::D, rank: Rank::R2 }].is_none());
+        assert!(moves[Square { file: File::E, rank: Rank::R2 }].is_none());
+        assert!(moves[Square { file: File::B, rank: Rank::R8 }].is_some());
+    }
+
+    #[test]
+    fn move_errors() {
+        let mut game = Game::new();
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::E, Rank::R3), to: sq(File::E, Rank::R4) }), Err(MoveError::EmptySquare));
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::E, Rank::R7), to: sq(File::E, Rank::R5) }), Err(MoveError::OpponentPiece));
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::A, Rank::R1), to: sq(File::A, Rank::R3) }), Err(MoveError::Collision { at: sq(File::A, Rank::R2) }));
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::B, Rank::R1), to: sq(File::B, Rank::R3) }), Err(MoveError::WrongPieceMovement));
+        assert_eq!(game.try_move(Move::Castle { side: CastlingSide::KingSide, color: Color::White }), Err(MoveError::Collision { at: sq(File::F, Rank::R1) }));
+
+        game.try_move(Move::Normal { from: sq(File::E, Rank::R2), to: sq(File::E, Rank::R3) }).unwrap();
+        game.try_move(Move::Normal { from: sq(File::E, Rank::R7), to: sq(File::E, Rank::R6) }).unwrap();
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::E, Rank::R3), to: sq(File::E, Rank::R5) }), Err(MoveError::PawnDubbleMove));
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::E, Rank::R3), to: sq(File::E, Rank::R2) }), Err(MoveError::WrongPieceMovement));
+
+        let mut game = Game::from_fen("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::E, Rank::R2), to: sq(File::D, Rank::R3) }), Err(MoveError::Pinned { by: sq(File::E, Rank::R7) }));
+    }
+
+    #[test]
+    fn move_errors_point_at_the_piece() {
+
+        //the rook on c1 is blocked by the own knight on e1 and the king walks into the bishop on a6
+        let mut game = Game::from_fen("4k3/8/b7/8/8/8/8/2R1NK2 w - - 0 1").unwrap();
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::C, Rank::R1), to: sq(File::G, Rank::R1) }), Err(MoveError::Collision { at: sq(File::E, Rank::R1) }));
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::F, Rank::R1), to: sq(File::F, Rank::R1) }), Err(MoveError::Collision { at: sq(File::F, Rank::R1) }));
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::F, Rank::R1), to: sq(File::E, Rank::R2) }), Err(MoveError::SelfCheck { attacker: sq(File::A, Rank::R6) }));
+
+        //a move that does not answer a check names the checking piece
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/3P4/r3K3 w - - 0 1").unwrap();
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::D, Rank::R2), to: sq(File::D, Rank::R3) }), Err(MoveError::SelfCheck { attacker: sq(File::A, Rank::R1) }));
+    }
+
+    #[test]
+    fn castling_errors() {
+        let castle = |side| Move::Castle { side, color: Color::White };
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K2R w K - 0 1").unwrap();
+        assert_eq!(game.try_move(castle(CastlingSide::QueenSide)), Err(MoveError::CastlingError { reason: CastlingBlock::NoRight }));
+
+        let mut game = Game::from_fen("3rk3/8/8/8/8/8/8/R3K1NR w KQ - 0 1").unwrap();
+        assert_eq!(game.try_move(castle(CastlingSide::KingSide)), Err(MoveError::Collision { at: sq(File::G, Rank::R1) }));
+        assert_eq!(game.try_move(castle(CastlingSide::QueenSide)), Err(MoveError::CastlingError { reason: CastlingBlock::AttackedSquare { square: sq(File::D, Rank::R1), attacker: sq(File::D, Rank::R8) } }));
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/3n4/8/R3K2R w KQ - 0 1").unwrap();
+        assert_eq!(game.try_move(castle(CastlingSide::KingSide)), Err(MoveError::CastlingError { reason: CastlingBlock::InCheck { attacker: sq(File::D, Rank::R3) } }));
+        assert_eq!(
+            MoveError::CastlingError { reason: CastlingBlock::InCheck { attacker: sq(File::D, Rank::R3) } }.to_string(),
+            "Castling error: the king is in check by the piece on d3"
+        );
+    }
+}
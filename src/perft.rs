@@ -0,0 +1,103 @@
+use crate::{Game, Move};
+
+impl Game {
+    /// Counts the leaf nodes of the legal move tree of the given depth from the current position. Used to check the correctness and speed of the move generator.
+    pub fn perft(&self, depth: u32) -> u64 {
+        self.clone().perft_nodes(depth)
+    }
+
+    /// Like perft but the count is broken down per legal move of the current position.
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        let mut game = self.clone();
+        game.legal_moves()
+            .into_iter()
+            .map(|mv| {
+                game.make_move(mv);
+                game.turn = game.turn.opposite();
+                let count = game.perft_nodes(depth.saturating_sub(1));
+                game.unmake_move();
+                (mv, count)
+            })
+            .collect()
+    }
+
+    ///makes and unmakes the moves on self without status updates
+    fn perft_nodes(&mut self, depth: u32) -> u64 {
+        let moves = self.legal_moves();
+        match depth {
+            0 => return 1,
+            1 => return moves.len() as u64,
+            _ => {}
+        }
+
+        let mut count = 0;
+        for mv in moves {
+            self.make_move(mv);
+            self.turn = self.turn.opposite();
+            count += self.perft_nodes(depth - 1);
+            self.unmake_move();
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_perft(fen: &str, counts: &[u64]) {
+        let game = Game::from_fen(fen).unwrap();
+        for (depth, count) in counts.iter().enumerate() {
+            assert_eq!(game.perft(depth as u32 + 1), *count, "{} at depth {}", fen, depth + 1);
+        }
+    }
+
+    #[test]
+    fn starting_position() {
+        check_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197281]);
+    }
+
+    #[test]
+    fn kiwipete() {
+        check_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn standard_positions() {
+        check_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]);
+        check_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]);
+        check_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]);
+        check_perft("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn en_passant_traps() {
+        //en passant that would expose the own king
+        check_perft("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", &[18, 92, 1670, 10138]);
+        check_perft("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", &[15, 126, 1928, 13931]);
+        check_perft("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", &[13, 102, 1266, 10276]);
+    }
+
+    #[test]
+    fn promotion_traps() {
+        check_perft("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", &[11, 133, 1442, 19174]);
+        check_perft("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", &[9, 40, 472, 2661]);
+        check_perft("8/P1k5/K7/8/8/8/8/8 w - - 0 1", &[6, 27, 273, 1329]);
+        check_perft("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", &[10, 25, 268, 926]);
+    }
+
+    #[test]
+    fn castling_traps() {
+        check_perft("5k2/8/8/8/8/8/8/4K2R w K - 0 1", &[15, 66, 1198, 6399]);
+        check_perft("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", &[16, 71, 1286, 7418]);
+        check_perft("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", &[26, 1141, 27826]);
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let game = Game::new();
+        let divide = game.divide(3);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().map(|(_, count)| count).sum::<u64>(), game.perft(3));
+    }
+}
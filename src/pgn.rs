@@ -0,0 +1,556 @@
+use std::error;
+use std::fmt;
+use crate::{Color, Game, Move, MoveError};
+use crate::fen::{FenError, STARTING_FEN};
+use crate::san::SanError;
+
+///The tags of the Seven Tag Roster in the order they are written.
+pub const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PgnResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    ///the game is still ongoing or the result is unknown, written as "*"
+    Unknown,
+}
+
+impl PgnResult {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PgnResult::WhiteWins => "1-0",
+            PgnResult::BlackWins => "0-1",
+            PgnResult::Draw => "1/2-1/2",
+            PgnResult::Unknown => "*",
+        }
+    }
+
+    ///the result of a game from its game status
+    pub fn from_game(game: &Game) -> PgnResult {
+        match game.result().map(|result| result.winner) {
+            Some(Some(Color::White)) => PgnResult::WhiteWins,
+            Some(Some(Color::Black)) => PgnResult::BlackWins,
+            Some(None) => PgnResult::Draw,
+            None => PgnResult::Unknown,
+        }
+    }
+
+    fn from_token(token: &str) -> Option<PgnResult> {
+        match token {
+            "1-0" => Some(PgnResult::WhiteWins),
+            "0-1" => Some(PgnResult::BlackWins),
+            "1/2-1/2" => Some(PgnResult::Draw),
+            "*" => Some(PgnResult::Unknown),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Annotation {
+    Comment(String),
+    ///a numeric annotation glyph, e.g. $1 for a good move
+    Nag(u8),
+}
+
+///A game read from or written to PGN. Variations are skipped when reading so only the main line is kept.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<Move>,
+    ///comments and NAGs together with the number of moves played before them.
+    pub annotations: Vec<(usize, Annotation)>,
+    pub result: PgnResult,
+}
+
+impl PgnGame {
+    fn empty() -> PgnGame {
+        PgnGame {
+            tags: vec![],
+            moves: vec![],
+            annotations: vec![],
+            result: PgnResult::Unknown,
+        }
+    }
+
+    /**
+    Creates a PGN game with the Seven Tag Roster from the moves played in game. Unknown tags are set to "?" and can be changed with set_tag.
+    The result is that of Game::result so resignations and agreed draws are kept. Chess960 games get a Variant tag and always carry their start position in the FEN tag.
+    */
+    pub fn from_game(game: &Game) -> PgnGame {
+        let result = PgnResult::from_game(game);
+        let mut pgn_game = PgnGame::empty();
+
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Date" => "????.??.??",
+                "Result" => result.as_str(),
+                _ => "?",
+            };
+            pgn_game.set_tag(name, value);
+        }
+        if game.chess960 {
+            pgn_game.set_tag("Variant", "Chess960");
+        }
+        if game.initial_fen != STARTING_FEN || game.chess960 {
+            pgn_game.set_tag("SetUp", "1");
+            pgn_game.set_tag("FEN", &game.initial_fen);
+        }
+
+        pgn_game.moves = game.moves.clone();
+        pgn_game.result = result;
+        pgn_game
+    }
+
+    ///the value of the tag name if it exists
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    ///sets the tag name to value, replacing the old value if the tag already exists
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some(tag) => tag.1 = String::from(value),
+            None => self.tags.push((String::from(name), String::from(value))),
+        }
+    }
+
+    ///the game in the starting position, taken from the FEN tag if there is one and played as Chess960 if the Variant tag says so
+    pub fn initial_game(&self) -> Result<Game, PgnError> {
+        let mut game = match self.tag("FEN") {
+            Some(fen) => Game::from_fen(fen).map_err(PgnError::InvalidFen)?,
+            None => Game::new(),
+        };
+        game.chess960 |= self.tag("Variant").is_some_and(|variant| variant.eq_ignore_ascii_case("Chess960"));
+        Ok(game)
+    }
+
+    ///the game after all the moves have been played from the starting position
+    pub fn replay(&self) -> Result<Game, PgnError> {
+        let mut game = self.initial_game()?;
+        for (ply, mv) in self.moves.iter().enumerate() {
+            let san = game.to_san(*mv);
+            game.try_move(*mv).map_err(|error| PgnError::MoveRefused { ply, san, error })?;
+        }
+        Ok(game)
+    }
+
+    ///Writes the game as PGN with the Seven Tag Roster first and the movetext wrapped at 80 characters.
+    pub fn to_pgn(&self) -> Result<String, PgnError> {
+        let mut pgn = String::new();
+
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => self.result.as_str(),
+                "Date" => self.tag(name).unwrap_or("????.??.??"),
+                _ => self.tag(name).unwrap_or("?"),
+            };
+            pgn.push_str(&format_tag(name, value));
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+                pgn.push_str(&format_tag(name, value));
+            }
+        }
+        pgn.push('\n');
+
+        let mut tokens: Vec<String> = vec![];
+        let mut game = self.initial_game()?;
+        let mut annotations = self.annotations.iter().peekable();
+        let mut needs_number = true;
+
+        for ply in 0..=self.moves.len() {
+            while let Some((_, annotation)) = annotations.next_if(|(i, _)| *i == ply) {
+                tokens.push(match annotation {
+                    Annotation::Comment(text) => format!("{{{}}}", text),
+                    Annotation::Nag(nag) => format!("${}", nag),
+                });
+                needs_number = true;
+            }
+
+            let Some(mv) = self.moves.get(ply) else { break };
+
+            match game.turn {
+                Color::White => tokens.push(format!("{}.", game.fullmove_number)),
+                Color::Black if needs_number => tokens.push(format!("{}...", game.fullmove_number)),
+                Color::Black => {},
+            }
+            needs_number = false;
+
+            let san = game.to_san(*mv);
+            game.try_move(*mv).map_err(|_| PgnError::IllegalMove { ply, san: san.clone(), error: SanError::IllegalMove })?;
+            tokens.push(san);
+        }
+        tokens.push(String::from(self.result.as_str()));
+
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > 80 {
+                pgn.push('\n');
+                line_length = 0;
+            }
+            else if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            line_length += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+
+        Ok(pgn)
+    }
+}
+
+fn format_tag(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+///Reads the first game of a PGN.
+pub fn read_game(pgn: &str) -> Result<PgnGame, PgnError> {
+    read_games(pgn)?.into_iter().next().ok_or(PgnError::NoGame)
+}
+
+///Reads all games of a PGN. The moves of every game are checked against the position so an illegal move returns an error.
+pub fn read_games(pgn: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut lexer = Lexer { chars: pgn.chars().collect(), pos: 0 };
+    let mut games = vec![];
+    let mut pgn_game = PgnGame::empty();
+    let mut game: Option<Game> = None;
+    let mut started = false;
+
+    while let Some(token) = lexer.next_token()? {
+        match token {
+            Token::Tag(name, value) => {
+                if game.is_some() {
+                    games.push(pgn_game);
+                    pgn_game = PgnGame::empty();
+                    game = None;
+                }
+                pgn_game.tags.push((name, value));
+            },
+            Token::Annotation(annotation) => pgn_game.annotations.push((pgn_game.moves.len(), annotation)),
+            Token::Result(result) => {
+                pgn_game.result = result;
+                games.push(pgn_game);
+                pgn_game = PgnGame::empty();
+                game = None;
+                started = false;
+                continue;
+            },
+            Token::San(san) => {
+                let position = match &mut game {
+                    Some(position) => position,
+                    None => game.insert(pgn_game.initial_game()?),
+                };
+                let ply = pgn_game.moves.len();
+                let mv = position.parse_san(&san).map_err(|error| PgnError::IllegalMove { ply, san: san.clone(), error })?;
+                position.try_move(mv).map_err(|error| PgnError::MoveRefused { ply, san, error })?;
+                pgn_game.moves.push(mv);
+            },
+        }
+        started = true;
+    }
+
+    if started {
+        games.push(pgn_game);
+    }
+
+    Ok(games)
+}
+
+enum Token {
+    Tag(String, String),
+    Annotation(Annotation),
+    San(String),
+    Result(PgnResult),
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let mut s = String::new();
+        while let Some(c) = self.peek().filter(|c| f(*c)) {
+            s.push(c);
+            self.pos += 1;
+        }
+        s
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, PgnError> {
+        loop {
+            self.skip_whitespace();
+            let line_start = self.pos == 0 || self.chars[self.pos - 1] == '\n';
+
+            let Some(c) = self.bump() else { return Ok(None) };
+            match c {
+                '%' if line_start => {
+                    self.take_while(|c| c != '\n');
+                },
+                '[' => return self.tag().map(Some),
+                '{' => {
+                    let text = self.take_while(|c| c != '}');
+                    if self.bump() != Some('}') {
+                        return Err(PgnError::UnterminatedComment);
+                    }
+                    return Ok(Some(Token::Annotation(Annotation::Comment(String::from(text.trim())))));
+                },
+                ';' => {
+                    let text = self.take_while(|c| c != '\n');
+                    return Ok(Some(Token::Annotation(Annotation::Comment(String::from(text.trim())))));
+                },
+                '$' => {
+                    let nag = self.take_while(|c| c.is_ascii_digit()).parse().map_err(|_| PgnError::InvalidNag)?;
+                    return Ok(Some(Token::Annotation(Annotation::Nag(nag))));
+                },
+                '(' => self.skip_variation()?,
+                ')' => return Err(PgnError::UnterminatedVariation),
+                _ => {
+                    self.pos -= 1;
+                    let symbol = self.take_while(|c| !c.is_whitespace() && !"[]{}();$".contains(c));
+
+                    if let Some(result) = PgnResult::from_token(&symbol) {
+                        return Ok(Some(Token::Result(result)));
+                    }
+
+                    //removes move numbers like "12." or "12..." that may be written together with the move, but not the zeros of "0-0"
+                    let rest = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+                    let san = match rest.is_empty() || rest.starts_with('.') {
+                        true => rest.trim_start_matches('.'),
+                        false => symbol.as_str(),
+                    };
+
+                    if !san.is_empty() {
+                        return Ok(Some(Token::San(String::from(san))));
+                    }
+                },
+            }
+        }
+    }
+
+    fn tag(&mut self) -> Result<Token, PgnError> {
+        self.skip_whitespace();
+        let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+        self.skip_whitespace();
+        if name.is_empty() || self.bump() != Some('"') {
+            return Err(PgnError::InvalidTag);
+        }
+
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('\\') => value.push(self.bump().ok_or(PgnError::InvalidTag)?),
+                Some('"') => break,
+                Some(c) => value.push(c),
+                None => return Err(PgnError::InvalidTag),
+            }
+        }
+
+        self.skip_whitespace();
+        if self.bump() != Some(']') {
+            return Err(PgnError::InvalidTag);
+        }
+        Ok(Token::Tag(name, value))
+    }
+
+    fn skip_variation(&mut self) -> Result<(), PgnError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.bump() {
+                Some('(') => depth += 1,
+                Some(')') => depth -= 1,
+                Some('{') => {
+                    self.take_while(|c| c != '}');
+                    self.bump();
+                },
+                Some(_) => {},
+                None => return Err(PgnError::UnterminatedVariation),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PgnError {
+    NoGame,
+    InvalidTag,
+    InvalidNag,
+    UnterminatedComment,
+    UnterminatedVariation,
+    InvalidFen(FenError),
+    ///the move at index ply of the main line does not match a legal move
+    IllegalMove {
+        ply: usize,
+        san: String,
+        error: SanError,
+    },
+    ///the move at index ply of the main line was refused by try_move, error says why
+    MoveRefused {
+        ply: usize,
+        san: String,
+        error: MoveError,
+    },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::NoGame => write!(f, "No game: the PGN does not contain any game"),
+            PgnError::InvalidTag => write!(f, "Invalid tag: tags have to be written like [Name \"value\"]"),
+            PgnError::InvalidNag => write!(f, "Invalid NAG: a NAG has to be a $ followed by a number from 0 to 255"),
+            PgnError::UnterminatedComment => write!(f, "Unterminated comment: a comment is missing its closing }}"),
+            PgnError::UnterminatedVariation => write!(f, "Unterminated variation: the parentheses of a variation don't match"),
+            PgnError::InvalidFen(e) => write!(f, "Invalid FEN tag: {}", e),
+            PgnError::IllegalMove { ply, san, error } => write!(f, "Illegal move: {} at ply {} could not be played. {}", san, ply + 1, error),
+            PgnError::MoveRefused { ply, san, error } => write!(f, "Move refused: {} at ply {} could not be played. {}", san, ply + 1, error),
+        }
+    }
+}
+
+impl error::Error for PgnError { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CastlingSide, GameStatus};
+    use crate::test_util::play;
+
+    const OPERA_GAME: &str = r#"[Event "Paris"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[Round "?"]
+[White "Paul Morphy"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+[ECO "C41"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 {This is a weak move already.} 4. dxe5 Bxf3 5. Qxf3
+dxe5 6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 c6 9. Bg5 b5 $6 10. Nxb5 cxb5 11. Bxb5+ Nbd7
+12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0
+"#;
+
+    #[test]
+    fn read_opera_game() {
+        let pgn_game = read_game(OPERA_GAME).unwrap();
+        assert_eq!(pgn_game.tag("White"), Some("Paul Morphy"));
+        assert_eq!(pgn_game.tag("ECO"), Some("C41"));
+        assert_eq!(pgn_game.moves.len(), 33);
+        assert_eq!(pgn_game.result, PgnResult::WhiteWins);
+        assert_eq!(pgn_game.annotations, vec![
+            (6, Annotation::Comment(String::from("This is a weak move already."))),
+            (18, Annotation::Nag(6)),
+        ]);
+
+        let game = pgn_game.replay().unwrap();
+        assert_eq!(game.game_status, GameStatus::Checkmate(Color::White));
+        assert_eq!(PgnResult::from_game(&game), PgnResult::WhiteWins);
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let pgn_game = read_game(OPERA_GAME).unwrap();
+        let pgn = pgn_game.to_pgn().unwrap();
+        assert_eq!(read_game(&pgn).unwrap(), pgn_game);
+        assert!(pgn.lines().all(|line| line.len() <= 80));
+        assert!(pgn.contains("3. d4 Bg4 {This is a weak move already.} 4. dxe5"));
+        assert!(pgn.contains("9. Bg5 b5 $6 10. Nxb5"));
+
+        let game = pgn_game.replay().unwrap();
+        let mut written = PgnGame::from_game(&game);
+        written.set_tag("White", "Paul Morphy");
+        let pgn = written.to_pgn().unwrap();
+        assert!(pgn.starts_with("[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"Paul Morphy\"]\n[Black \"?\"]\n[Result \"1-0\"]\n\n1. e4 e5"));
+        assert!(pgn.ends_with("17. Rd8# 1-0\n"));
+    }
+
+    #[test]
+    fn variations_and_multiple_games() {
+        let pgn = "1. e4 (1. d4 d5 (1... Nf6) 2. c4) 1... e5 {main line} 2. Nf3 *\n\n\
+            [FEN \"4k3/8/8/8/8/8/8/R3K3 w Q - 0 1\"]\n[SetUp \"1\"]\n\n1. O-O-O Kf7 1/2-1/2";
+        let games = read_games(pgn).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].moves.len(), 3);
+        assert_eq!(games[0].result, PgnResult::Unknown);
+        assert_eq!(games[1].moves.len(), 2);
+        assert_eq!(games[1].result, PgnResult::Draw);
+
+        let written = PgnGame::from_game(&games[1].replay().unwrap()).to_pgn().unwrap();
+        assert!(written.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 w Q - 0 1\"]\n"));
+        assert!(written.contains("1. O-O-O Kf7 *"));
+    }
+
+    #[test]
+    fn results_and_variants_from_the_game() {
+        let mut game = Game::new();
+        play(&mut game, "e4").unwrap();
+        game.resign(Color::White).unwrap();
+        let written = PgnGame::from_game(&game).to_pgn().unwrap();
+        assert!(written.contains("[Result \"0-1\"]"));
+        assert!(written.ends_with("1. e4 0-1\n"));
+
+        let mut game = Game::new();
+        game.offer_draw(Color::White).unwrap();
+        game.accept_draw(Color::Black).unwrap();
+        assert_eq!(PgnGame::from_game(&game).result, PgnResult::Draw);
+
+        let mut game = Game::new_chess960(0);
+        play(&mut game, "Nd3").unwrap();
+        let pgn_game = PgnGame::from_game(&game);
+        assert_eq!(pgn_game.tag("Variant"), Some("Chess960"));
+        assert_eq!(pgn_game.tag("SetUp"), Some("1"));
+        assert_eq!(pgn_game.tag("FEN"), Some("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"));
+        let read = read_game(&pgn_game.to_pgn().unwrap()).unwrap().replay().unwrap();
+        assert!(read.chess960);
+        assert_eq!(read.to_fen(), game.to_fen());
+    }
+
+    #[test]
+    fn castles_written_with_zeros() {
+        let pgn_game = read_game("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 5. d3 d6 6. Nc3 Bg4 7. h3 Qd7 8. hxg4 0-0-0 *").unwrap();
+        assert_eq!(pgn_game.moves[6], Move::Castle { side: CastlingSide::KingSide, color: Color::White });
+        assert_eq!(pgn_game.moves[15], Move::Castle { side: CastlingSide::QueenSide, color: Color::Black });
+        assert!(pgn_game.to_pgn().unwrap().contains("4. O-O Nf6"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(read_game(""), Err(PgnError::NoGame));
+        assert_eq!(read_game("[Event \"?\"\n1. e4"), Err(PgnError::InvalidTag));
+        assert_eq!(read_game("1. e4 {unterminated"), Err(PgnError::UnterminatedComment));
+        assert_eq!(
+            read_game("1. e4 e5 2. Ke3"),
+            Err(PgnError::IllegalMove { ply: 2, san: String::from("Ke3"), error: SanError::IllegalMove })
+        );
+
+        //the moves of a PgnGame made by hand are only checked when it is replayed
+        let mut pgn_game = read_game("1. e4 e5 *").unwrap();
+        pgn_game.moves.push(Move::Normal { from: "e4".parse().unwrap(), to: "e6".parse().unwrap() });
+        assert!(matches!(pgn_game.replay(), Err(PgnError::MoveRefused { ply: 2, error: MoveError::PawnDubbleMove, .. })));
+    }
+}
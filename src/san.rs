@@ -0,0 +1,304 @@
+use std::error;
+use std::fmt;
+use crate::{CastlingSide, Color, Game, GameStatus, Move, Piece, PieceTypes};
+use crate::util::{Rank, Square};
+
+impl Game {
+    /**
+    Parses a move in standard algebraic notation (SAN), e.g. "Nbd7", "exd6", "e8=Q+" or "O-O", against the current position.
+    Check and annotation suffixes are ignored. Promotions are returned as Move::Promotion and have to name the promotion piece.
+    */
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+
+        match san {
+            "O-O" | "0-0" => return self.legal_san_move(Move::Castle { side: CastlingSide::KingSide, color: self.turn }),
+            "O-O-O" | "0-0-0" => return self.legal_san_move(Move::Castle { side: CastlingSide::QueenSide, color: self.turn }),
+            _ => {}
+        }
+
+        let mut chars: Vec<char> = san.chars().collect();
+
+        let mut promotion = None;
+        if let Some(piece) = chars.last().and_then(|c| piece_from_san(*c)) {
+            promotion = Some(piece);
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+
+        if chars.len() < 2 {
+            return Err(SanError::InvalidSan);
+        }
+        let to_str: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let to: Square = to_str.parse().map_err(|_| SanError::InvalidSan)?;
+
+        let mut rest = chars.as_slice();
+        let piece = match rest.first().and_then(|c| piece_from_san(*c)) {
+            Some(piece) => {
+                rest = &rest[1..];
+                piece
+            },
+            None => PieceTypes::Pawn(false),
+        };
+        if let Some((&('x' | ':'), start)) = rest.split_last() {
+            rest = start;
+        }
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in rest {
+            match c {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => from_file = Some(*c as i32 - 'a' as i32),
+                '1'..='8' if from_rank.is_none() => from_rank = Some(*c as i32 - '1' as i32),
+                _ => return Err(SanError::InvalidSan),
+            }
+        }
+
+        let is_pawn = matches!(piece, PieceTypes::Pawn(_));
+        if promotion.is_some() && !is_pawn {
+            return Err(SanError::InvalidSan);
+        }
+
+        let last_row = match self.turn {
+            Color::White => Rank::R8,
+            Color::Black => Rank::R1,
+        };
+
+        let mut found = None;
+        for from in self.san_candidates(piece, to) {
+            if from_file.is_some_and(|f| f != from.file.num()) || from_rank.is_some_and(|r| r != from.rank.num()) {
+                continue;
+            }
+
+            let mv = match promotion {
+                _ if !is_pawn || to.rank != last_row => Move::Normal { from, to },
+                Some(piece) => Move::Promotion { from, to, piece },
+                None => return Err(SanError::MissingPromotion),
+            };
+            if promotion.is_some() && !matches!(mv, Move::Promotion { .. }) {
+                return Err(SanError::IllegalMove);
+            }
+
+            if self.after_move(mv).is_some() {
+                if found.is_some() {
+                    return Err(SanError::AmbiguousMove);
+                }
+                found = Some(mv);
+            }
+        }
+
+        found.ok_or(SanError::IllegalMove)
+    }
+
+    /**
+    Formats a move in standard algebraic notation (SAN) with a "+" or "#" suffix if it gives check or checkmate.
+    The move should be legal in the current position. A Normal pawn move to the last rank has no promotion piece yet and is written without one.
+    */
+    pub fn to_san(&self, mv: Move) -> String {
+        let mut san = match mv {
+            Move::Castle { side: CastlingSide::KingSide, .. } => String::from("O-O"),
+            Move::Castle { side: CastlingSide::QueenSide, .. } => String::from("O-O-O"),
+            Move::Normal { from, to } | Move::Promotion { from, to, .. } => {
+                let mut san = String::new();
+                let piece = self.board[from].map_or(PieceTypes::Pawn(false), |p| p.piece);
+                let capture = self.board[to].is_some() || self.en_passant_capture(from, to).is_some();
+
+                match piece {
+                    PieceTypes::Pawn(_) => {
+                        if capture {
+                            san.push_str(&from.to_string()[..1]);
+                        }
+                    },
+                    _ => {
+                        san.push(piece_to_san(piece));
+
+                        let others: Vec<Square> = self.san_candidates(piece, to)
+                            .into_iter()
+                            .filter(|s| *s != from)
+                            .collect();
+                        let from_str = from.to_string();
+                        if !others.is_empty() {
+                            if others.iter().all(|s| s.file != from.file) {
+                                san.push_str(&from_str[..1]);
+                            }
+                            else if others.iter().all(|s| s.rank != from.rank) {
+                                san.push_str(&from_str[1..]);
+                            }
+                            else {
+                                san.push_str(&from_str);
+                            }
+                        }
+                    },
+                }
+
+                if capture {
+                    san.push('x');
+                }
+                san.push_str(&to.to_string());
+
+                if let Move::Promotion { piece, .. } = mv {
+                    san.push('=');
+                    san.push(piece_to_san(piece));
+                }
+
+                san
+            },
+        };
+
+        if let Some(game) = self.after_move(mv) {
+            if matches!(game.game_status, GameStatus::Checkmate(_)) {
+                san.push('#');
+            }
+            else if game.check {
+                san.push('+');
+            }
+        }
+
+        san
+    }
+
+    ///the squares of the pieces of the player in turn with the type piece that can move to the square to.
+    fn san_candidates(&self, piece: PieceTypes, to: Square) -> Vec<Square> {
+        let mut candidates: Vec<Square> = vec![];
+        for mv in self.legal_moves() {
+            let from = match mv {
+                Move::Normal { from, to: t } | Move::Promotion { from, to: t, .. } if t == to => from,
+                _ => continue,
+            };
+            let matches = match (self.board[from], piece) {
+                (Some(Piece { piece: PieceTypes::Pawn(_), .. }), PieceTypes::Pawn(_)) => true,
+                (Some(p), _) => p.piece == piece,
+                (None, _) => false,
+            };
+            if matches && !candidates.contains(&from) {
+                candidates.push(from);
+            }
+        }
+        candidates
+    }
+
+    fn legal_san_move(&self, mv: Move) -> Result<Move, SanError> {
+        self.after_move(mv).map(|_| mv).ok_or(SanError::IllegalMove)
+    }
+
+    ///a copy of the game after the move is made or None if the move is illegal.
+    pub(crate) fn after_move(&self, mv: Move) -> Option<Game> {
+        let mut game = self.clone();
+        //the copy is only used to look at the position so the clock can not be allowed to end the game
+        game.clock = None;
+        game.try_move(mv).ok().map(|_| game)
+    }
+}
+
+///the SAN letter of a piece. Pawns don't have a letter and return 'P'.
+pub(crate) fn piece_to_san(piece: PieceTypes) -> char {
+    match piece {
+        PieceTypes::Pawn(_) => 'P',
+        PieceTypes::Knight => 'N',
+        PieceTypes::Bishop => 'B',
+        PieceTypes::Rook => 'R',
+        PieceTypes::Queen => 'Q',
+        PieceTypes::King => 'K',
+    }
+}
+
+fn piece_from_san(c: char) -> Option<PieceTypes> {
+    match c {
+        'N' => Some(PieceTypes::Knight),
+        'B' => Some(PieceTypes::Bishop),
+        'R' => Some(PieceTypes::Rook),
+        'Q' => Some(PieceTypes::Queen),
+        'K' => Some(PieceTypes::King),
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SanError {
+    InvalidSan,
+    IllegalMove,
+    AmbiguousMove,
+    MissingPromotion,
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SanError::InvalidSan => write!(f, "Invalid SAN: the move is not written in standard algebraic notation"),
+            SanError::IllegalMove => write!(f, "Illegal move: no legal move in the position matches the SAN"),
+            SanError::AmbiguousMove => write!(f, "Ambiguous move: more than one legal move matches the SAN"),
+            SanError::MissingPromotion => write!(f, "Missing promotion: a pawn move to the last rank has to name the promotion piece"),
+        }
+    }
+}
+
+impl error::Error for SanError { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sq;
+    use crate::util::File;
+
+    #[test]
+    fn parse_simple_moves() {
+        let game = Game::new();
+        assert_eq!(game.parse_san("e4"), Ok(Move::Normal { from: sq(File::E, Rank::R2), to: sq(File::E, Rank::R4) }));
+        assert_eq!(game.parse_san("Nf3"), Ok(Move::Normal { from: sq(File::G, Rank::R1), to: sq(File::F, Rank::R3) }));
+        assert_eq!(game.parse_san("Nf3!?"), Ok(Move::Normal { from: sq(File::G, Rank::R1), to: sq(File::F, Rank::R3) }));
+        assert_eq!(game.parse_san("e5"), Err(SanError::IllegalMove));
+        assert_eq!(game.parse_san("O-O"), Err(SanError::IllegalMove));
+        assert_eq!(game.parse_san("Zz9"), Err(SanError::InvalidSan));
+    }
+
+    #[test]
+    fn parse_disambiguation() {
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/4K3/R6R b kq - 0 1").unwrap();
+        assert_eq!(game.parse_san("O-O-O"), Ok(Move::Castle { side: CastlingSide::QueenSide, color: Color::Black }));
+        assert_eq!(game.parse_san("Rd8"), Ok(Move::Normal { from: sq(File::A, Rank::R8), to: sq(File::D, Rank::R8) }));
+        assert_eq!(game.parse_san("Rf8"), Ok(Move::Normal { from: sq(File::H, Rank::R8), to: sq(File::F, Rank::R8) }));
+
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/4K3/R6R w kq - 0 1").unwrap();
+        assert_eq!(game.parse_san("Rd1"), Err(SanError::AmbiguousMove));
+        assert_eq!(game.parse_san("Rad1"), Ok(Move::Normal { from: sq(File::A, Rank::R1), to: sq(File::D, Rank::R1) }));
+        assert_eq!(game.to_san(Move::Normal { from: sq(File::H, Rank::R1), to: sq(File::D, Rank::R1) }), "Rhd1");
+
+        let game = Game::from_fen("4k3/8/8/8/1N6/8/1N6/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.parse_san("N4d3"), Ok(Move::Normal { from: sq(File::B, Rank::R4), to: sq(File::D, Rank::R3) }));
+        assert_eq!(game.to_san(Move::Normal { from: sq(File::B, Rank::R2), to: sq(File::D, Rank::R3) }), "N2d3");
+    }
+
+    #[test]
+    fn promotions_and_en_passant() {
+        let game = Game::from_fen("7k/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let promotion = Move::Promotion { from: sq(File::A, Rank::R7), to: sq(File::A, Rank::R8), piece: PieceTypes::Queen };
+        assert_eq!(game.parse_san("a8=Q+"), Ok(promotion));
+        assert_eq!(game.parse_san("a8Q"), Ok(promotion));
+        assert_eq!(game.parse_san("a8"), Err(SanError::MissingPromotion));
+        assert_eq!(game.to_san(promotion), "a8=Q+");
+
+        let game = Game::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap();
+        let capture = Move::Normal { from: sq(File::E, Rank::R5), to: sq(File::F, Rank::R6) };
+        assert_eq!(game.parse_san("exf6"), Ok(capture));
+        assert_eq!(game.to_san(capture), "exf6");
+    }
+
+    #[test]
+    fn check_and_mate_suffixes() {
+        let mut game = Game::new();
+        for san in ["f3", "e5", "g4"] {
+            let mv = game.parse_san(san).unwrap();
+            assert_eq!(game.to_san(mv), san);
+            game.try_move(mv).unwrap();
+        }
+        let mate = game.parse_san("Qh4").unwrap();
+        assert_eq!(game.to_san(mate), "Qh4#");
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert_eq!(game.to_san(Move::Castle { side: CastlingSide::QueenSide, color: Color::White }), "O-O-O");
+        assert_eq!(game.to_san(Move::Normal { from: sq(File::A, Rank::R1), to: sq(File::A, Rank::R8) }), "Ra8+");
+    }
+}
@@ -0,0 +1,447 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{Game, Move, PieceTypes};
+use crate::bitboard;
+use crate::eval::{DefaultEvaluator, Evaluator, piece_value};
+
+///the score of being checkmated at the root. Mate in n plies is scored MATE - n.
+pub const MATE: i32 = 30000;
+///scores above this are mates
+pub const MATE_BOUND: i32 = MATE - 1000;
+const INFINITY: i32 = MATE + 1;
+const MAX_PLY: usize = 128;
+
+///How long a search may run. The search stops at whichever limit is reached first.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchLimits {
+    ///the deepest iteration of the iterative deepening
+    pub depth: u32,
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits { depth, movetime: None, nodes: None }
+    }
+
+    pub fn movetime(movetime: Duration) -> SearchLimits {
+        SearchLimits { depth: MAX_PLY as u32 - 1, movetime: Some(movetime), nodes: None }
+    }
+}
+
+///The result of the deepest completed iteration of a search.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchResult {
+    ///None when the side to move has no legal moves
+    pub best_move: Option<Move>,
+    ///in centipawns from the view of the side to move, mates are scored with MATE
+    pub score: i32,
+    pub depth: u32,
+    ///the principal variation, the line both sides are expected to play starting with best_move
+    pub pv: Vec<Move>,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+impl SearchResult {
+    ///the number of moves until mate, negative if the side to move gets mated, or None if the score is not a mate
+    pub fn mate_in(&self) -> Option<i32> {
+        match self.score {
+            s if s > MATE_BOUND => Some((MATE - s + 1) / 2),
+            s if s < -MATE_BOUND => Some(-(MATE + s + 1) / 2),
+            _ => None,
+        }
+    }
+}
+
+/**
+A negamax alpha-beta searcher with iterative deepening and quiescence search.
+Moves are ordered by the principal variation of the previous iteration, captures by MVV-LVA, killer moves and the history heuristic.
+The killer and history tables are kept between searches and can be cleared with new_game.
+Positions are scored by the evaluator E, which is the DefaultEvaluator unless another one is given with with_evaluator.
+*/
+pub struct Searcher<E: Evaluator = DefaultEvaluator> {
+    evaluator: E,
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    history: [[[u32; 64]; 64]; 2],
+    stop: Arc<AtomicBool>,
+    nodes: u64,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+    ///the keys of the positions from the start of the game to the current node, used to find repetitions
+    path: Vec<u64>,
+    previous_pv: Vec<Move>,
+    stopped: bool,
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Searcher {
+    pub fn new() -> Searcher {
+        Searcher::with_evaluator(DefaultEvaluator)
+    }
+}
+
+impl<E: Evaluator> Searcher<E> {
+    ///a searcher that scores positions with evaluator
+    pub fn with_evaluator(evaluator: E) -> Searcher<E> {
+        Searcher {
+            evaluator,
+            killers: [[None; 2]; MAX_PLY],
+            history: [[[0; 64]; 64]; 2],
+            stop: Arc::new(AtomicBool::new(false)),
+            nodes: 0,
+            node_limit: None,
+            deadline: None,
+            path: vec![],
+            previous_pv: vec![],
+            stopped: false,
+        }
+    }
+
+    ///clears the move ordering tables
+    pub fn new_game(&mut self) {
+        self.killers = [[None; 2]; MAX_PLY];
+        self.history = [[[0; 64]; 64]; 2];
+    }
+
+    ///a flag that stops a running search from another thread when set to true. The search resets it when it starts.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    ///searches the game until a limit is reached and returns the result of the deepest completed iteration
+    pub fn search(&mut self, game: &Game, limits: SearchLimits) -> SearchResult {
+        self.search_with_info(game, limits, |_| {})
+    }
+
+    ///like search but info is called with the result of every completed iteration
+    pub fn search_with_info(&mut self, game: &Game, limits: SearchLimits, mut info: impl FnMut(&SearchResult)) -> SearchResult {
+        let start = Instant::now();
+        self.stop.store(false, Ordering::Relaxed);
+        self.stopped = false;
+        self.nodes = 0;
+        self.node_limit = limits.nodes;
+        self.deadline = limits.movetime.map(|time| start + time);
+        self.path = game.positions.clone();
+        self.path.pop();
+        self.previous_pv = vec![];
+
+        let mut game = game.clone();
+        let mut result = SearchResult {
+            best_move: game.legal_moves().first().copied(),
+            score: 0,
+            depth: 0,
+            pv: vec![],
+            nodes: 0,
+            time: Duration::ZERO,
+        };
+        if result.best_move.is_none() {
+            return result;
+        }
+
+        for depth in 1..=limits.depth.clamp(1, MAX_PLY as u32 - 1) {
+            let mut pv = vec![];
+            let score = self.negamax(&mut game, depth as i32, 0, -INFINITY, INFINITY, &mut pv);
+
+            //a stopped iteration is only used if it found a move, which is at least as good as the previous best because the previous pv is searched first
+            if self.stopped && pv.is_empty() {
+                break;
+            }
+
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                score,
+                depth,
+                pv: pv.clone(),
+                nodes: self.nodes,
+                time: start.elapsed(),
+            };
+            self.previous_pv = pv;
+
+            if self.stopped {
+                break;
+            }
+            info(&result);
+
+            if result.score.abs() > MATE_BOUND && MATE - result.score.abs() <= depth as i32 {
+                break;
+            }
+        }
+
+        result.nodes = self.nodes;
+        result.time = start.elapsed();
+        result
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if self.nodes.is_multiple_of(1024) {
+            self.stopped = self.stop.load(Ordering::Relaxed)
+                || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                || self.node_limit.is_some_and(|limit| self.nodes >= limit);
+        }
+        self.stopped
+    }
+
+    fn negamax(&mut self, game: &mut Game, mut depth: i32, ply: usize, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        pv.clear();
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        let key = game.hash();
+        if ply > 0 && (game.halfmove_clock >= 100 || game.insufficient_material() || self.path.contains(&key)) {
+            return 0;
+        }
+
+        let in_check = game.in_check(game.turn);
+        if in_check {
+            depth += 1;
+        }
+        if depth <= 0 || ply >= MAX_PLY - 1 {
+            return self.quiescence(game, ply, alpha, beta);
+        }
+
+        let mut moves = game.legal_moves();
+        if moves.is_empty() {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+        self.order_moves(game, &mut moves, ply);
+
+        self.path.push(key);
+        let mut child_pv = vec![];
+        for mv in moves {
+            let quiet = !is_noisy(game, mv);
+            game.make_move(mv);
+            game.turn = game.turn.opposite();
+            let score = -self.negamax(game, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            game.unmake_move();
+
+            if self.stopped {
+                break;
+            }
+
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.append(&mut child_pv);
+            }
+
+            if alpha >= beta {
+                if quiet {
+                    self.store_killer(mv, ply);
+                    if let Some((from, to)) = squares(mv) {
+                        self.history[bitboard::color_index(game.turn)][from][to] += (depth * depth) as u32;
+                    }
+                }
+                break;
+            }
+        }
+        self.path.pop();
+
+        alpha
+    }
+
+    ///searches captures and promotions until the position is quiet so the evaluation is not taken in the middle of an exchange
+    fn quiescence(&mut self, game: &mut Game, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        let in_check = game.in_check(game.turn);
+        let mut moves = game.legal_moves();
+        if moves.is_empty() {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+
+        //when in check every evasion is searched since standing still is not an option
+        if !in_check {
+            let stand_pat = self.evaluator.evaluate(game);
+            if stand_pat >= beta || ply >= MAX_PLY - 1 {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            moves.retain(|mv| is_noisy(game, *mv));
+        }
+        moves.sort_by_cached_key(|mv| -mvv_lva(game, *mv));
+
+        for mv in moves {
+            game.make_move(mv);
+            game.turn = game.turn.opposite();
+            let score = -self.quiescence(game, ply + 1, -beta, -alpha);
+            game.unmake_move();
+
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+
+    fn store_killer(&mut self, mv: Move, ply: usize) {
+        if self.killers[ply][0] != Some(mv) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(mv);
+        }
+    }
+
+    fn order_moves(&self, game: &Game, moves: &mut [Move], ply: usize) {
+        let pv_move = self.previous_pv.get(ply).copied();
+        let color = bitboard::color_index(game.turn);
+
+        moves.sort_by_cached_key(|mv| {
+            let score = if Some(*mv) == pv_move {
+                3_000_000
+            }
+            else if is_noisy(game, *mv) {
+                2_000_000 + mvv_lva(game, *mv)
+            }
+            else if self.killers[ply][0] == Some(*mv) {
+                1_500_000
+            }
+            else if self.killers[ply][1] == Some(*mv) {
+                1_400_000
+            }
+            else {
+                squares(*mv).map_or(0, |(from, to)| self.history[color][from][to].min(1_000_000) as i32)
+            };
+            -score
+        });
+    }
+}
+
+///the board indexes of the from and to squares of a move that is not castling
+fn squares(mv: Move) -> Option<(usize, usize)> {
+    match mv {
+        Move::Normal { from, to } | Move::Promotion { from, to, .. } => Some((bitboard::index(from), bitboard::index(to))),
+        Move::Castle { .. } => None,
+    }
+}
+
+///captures and promotions
+fn is_noisy(game: &Game, mv: Move) -> bool {
+    match mv {
+        Move::Promotion { .. } => true,
+        Move::Normal { from, to } => game.board[to].is_some() || game.en_passant_capture(from, to).is_some(),
+        Move::Castle { .. } => false,
+    }
+}
+
+///most valuable victim, least valuable attacker. Higher is searched first.
+fn mvv_lva(game: &Game, mv: Move) -> i32 {
+    let (from, to) = match mv {
+        Move::Normal { from, to } | Move::Promotion { from, to, .. } => (from, to),
+        Move::Castle { .. } => return 0,
+    };
+
+    let victim = match game.board[to] {
+        Some(piece) => piece_value(piece.piece),
+        None if game.en_passant_capture(from, to).is_some() => piece_value(PieceTypes::Pawn(true)),
+        None => 0,
+    };
+    let attacker = game.board[from].map_or(0, |piece| piece_value(piece.piece));
+    let promotion = match mv {
+        Move::Promotion { piece, .. } => piece_value(piece),
+        _ => 0,
+    };
+
+    (victim + promotion) * 10 - attacker / 10
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{File, Rank, Square};
+
+    fn search(fen: &str, depth: u32) -> SearchResult {
+        Searcher::new().search(&Game::from_fen(fen).unwrap(), SearchLimits::depth(depth))
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert_eq!(result.best_move, Some(Move::Normal { from: Square { file: File::A, rank: Rank::R1 }, to: Square { file: File::A, rank: Rank::R8 } }));
+        assert_eq!(result.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        //1. Rd8+ Rxd8 2. Rxd8#
+        let result = search("1r4k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1", 4);
+        assert_eq!(result.mate_in(), Some(2));
+        assert_eq!(result.pv.len(), 3);
+    }
+
+    #[test]
+    fn wins_material_and_avoids_losing_it() {
+        //the queen on d5 is hanging
+        let result = search("4k3/8/8/3q4/8/2N5/4P3/4K3 w - - 0 1", 3);
+        assert_eq!(result.best_move, Some(Move::Normal { from: Square { file: File::C, rank: Rank::R3 }, to: Square { file: File::D, rank: Rank::R5 } }));
+        assert!(result.score > 300);
+
+        //taking the pawn loses the queen to the pawn on e6
+        let result = search("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", 3);
+        assert_ne!(result.best_move, Some(Move::Normal { from: Square { file: File::D, rank: Rank::R1 }, to: Square { file: File::D, rank: Rank::R5 } }));
+    }
+
+    #[test]
+    fn pv_is_legal() {
+        let game = Game::new();
+        let result = Searcher::new().search(&game, SearchLimits::depth(4));
+        assert_eq!(result.depth, 4);
+        assert_eq!(result.best_move, result.pv.first().copied());
+
+        let mut replay = game.clone();
+        for mv in &result.pv {
+            replay.try_move(*mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn custom_evaluator() {
+        //an evaluator that only wants the white king on h1 and ignores material
+        let corner = |game: &Game| {
+            let on_h1 = game.board[Square { file: File::H, rank: Rank::R1 }].is_some_and(|p| p.piece == PieceTypes::King);
+            let score = if on_h1 { 100 } else { 0 };
+            if game.turn == crate::Color::White { score } else { -score }
+        };
+        let game = Game::from_fen("4k3/4p3/8/8/8/8/4P3/6K1 w - - 0 1").unwrap();
+        let result = Searcher::with_evaluator(corner).search(&game, SearchLimits::depth(3));
+        assert_eq!(result.score, 100);
+    }
+
+    #[test]
+    fn stalemate_and_limits() {
+        let result = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+        assert_eq!(result.best_move, None);
+
+        let mut searcher = Searcher::new();
+        let result = searcher.search(&Game::new(), SearchLimits { depth: 64, movetime: None, nodes: Some(5000) });
+        assert!(result.best_move.is_some());
+        assert!(result.depth < 64);
+
+        let result = searcher.search(&Game::new(), SearchLimits::movetime(Duration::from_millis(50)));
+        assert!(result.best_move.is_some());
+        assert!(result.time < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,281 @@
+//Serde support for the types that have a string form, the clock and Game. Every other public type derives the serde traits. The JSON shape is described in the README.
+
+use std::fmt;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{self, Visitor};
+use crate::{Color, Game, GameResult, GameStatus, Move};
+use crate::clock::{Clock, ClockState};
+use crate::fen;
+use crate::util::{Board, File, Rank, Square};
+
+///deserializes a value from a string with parse
+fn deserialize_str<'de, D, T, F>(deserializer: D, expecting: &'static str, parse: F) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    F: FnOnce(&str) -> Option<T>,
+{
+    struct StrVisitor(&'static str);
+
+    impl<'de> Visitor<'de> for StrVisitor {
+        type Value = String;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.0)
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<String, E> {
+            Ok(value.to_string())
+        }
+    }
+
+    let value = deserializer.deserialize_str(StrVisitor(expecting))?;
+    parse(&value).ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&value), &expecting))
+}
+
+impl Serialize for Square {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Square {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_str(deserializer, "a square like \"e4\"", |s| s.parse().ok())
+    }
+}
+
+impl Serialize for File {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for File {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_str(deserializer, "a file from \"a\" to \"h\"", |s| s.parse().ok())
+    }
+}
+
+impl Serialize for Rank {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.num() as u8 + 1)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rank {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rank = u8::deserialize(deserializer)?;
+        Rank::try_from(rank as i32 - 1).map_err(|_| de::Error::invalid_value(de::Unexpected::Unsigned(rank as u64), &"a rank from 1 to 8"))
+    }
+}
+
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_str(deserializer, "a move in UCI like \"e2e4\" or \"e7e8q\"", |s| s.parse().ok())
+    }
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&fen::board_to_string(self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_str(deserializer, "the piece placement of a FEN", |s| fen::parse_board(s).ok())
+    }
+}
+
+impl Serialize for Clock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.state().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Clock {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Clock::from_state(ClockState::deserialize(deserializer)?).ok_or_else(|| de::Error::custom("the clock is in a stage that its time control does not have"))
+    }
+}
+
+#[derive(Serialize)]
+struct GameOut<'a> {
+    initial_fen: &'a str,
+    ///the moves in UCI notation, so castles are written with the squares of the king like "e1g1"
+    moves: Vec<String>,
+    chess960: bool,
+    ///None while a pawn waits on the last rank for promote, since that is not a valid FEN
+    fen: Option<String>,
+    game_status: GameStatus,
+    draw_offer: Option<Color>,
+    result: Option<GameResult>,
+    clock: Option<ClockState>,
+}
+
+#[derive(Deserialize)]
+struct GameIn {
+    initial_fen: String,
+    moves: Vec<String>,
+    #[serde(default)]
+    chess960: bool,
+    #[serde(default)]
+    game_status: Option<GameStatus>,
+    #[serde(default)]
+    draw_offer: Option<Color>,
+    #[serde(default)]
+    clock: Option<Clock>,
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        //a castle is written from the position it was played in, so the moves are played again from the start
+        let mut replay = Game::from_fen(&self.initial_fen).map_err(serde::ser::Error::custom)?;
+        replay.chess960 = self.chess960;
+        let mut moves = Vec::with_capacity(self.moves.len());
+        for mv in &self.moves {
+            moves.push(replay.to_uci(*mv));
+            replay.try_move(*mv).map_err(serde::ser::Error::custom)?;
+        }
+
+        GameOut {
+            initial_fen: &self.initial_fen,
+            moves,
+            chess960: self.chess960,
+            fen: (self.game_status != GameStatus::Promoting).then(|| self.to_fen()),
+            game_status: self.game_status,
+            draw_offer: self.draw_offer,
+            result: self.result(),
+            clock: self.clock.as_ref().map(Clock::state),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = GameIn::deserialize(deserializer)?;
+
+        let mut game = Game::from_fen(&data.initial_fen).map_err(de::Error::custom)?;
+        game.chess960 |= data.chess960;
+        //try_move plays a king move that castles as the castle and leaves a pawn move to the last rank without a piece waiting for promote
+        for (ply, uci) in data.moves.iter().enumerate() {
+            let mv: Move = uci.parse().map_err(|error| de::Error::custom(format_args!("move {} {}: {}", ply + 1, uci, error)))?;
+            game.try_move(mv).map_err(|error| de::Error::custom(format_args!("move {} {}: {}", ply + 1, uci, error)))?;
+        }
+
+        //resignations, agreed draws, adjudications and timeouts are not the result of a move
+        if let Some(status) = data.game_status {
+            if game.game_status == GameStatus::Ongoing && !matches!(status, GameStatus::Ongoing | GameStatus::Promoting) {
+                game.game_status = status;
+            }
+        }
+        if game.result().is_none() {
+            game.draw_offer = data.draw_offer;
+        }
+        game.clock = data.clock;
+
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CastlingSide, Color, Piece, PieceTypes};
+    use crate::clock::TimeControl;
+    use std::time::Duration;
+    use crate::test_util::play;
+
+    #[test]
+    fn squares_and_moves_are_strings() {
+        let e4 = "e4".parse().unwrap();
+        assert_eq!(serde_json::to_string(&e4).unwrap(), "\"e4\"");
+        assert_eq!(serde_json::from_str::<Square>("\"e4\"").unwrap(), e4);
+        assert!(serde_json::from_str::<Square>("\"i9\"").is_err());
+
+        let moves = vec![
+            Move::Normal { from: "e2".parse().unwrap(), to: e4 },
+            Move::Promotion { from: "b7".parse().unwrap(), to: "a8".parse().unwrap(), piece: PieceTypes::Knight },
+            Move::Castle { side: CastlingSide::QueenSide, color: Color::White },
+        ];
+        let json = serde_json::to_string(&moves).unwrap();
+        assert_eq!(json, r#"["e2e4","b7a8n","e1c1"]"#);
+        //a castle is read back as the king move, which try_move plays as the castle
+        assert_eq!(serde_json::from_str::<Vec<Move>>(&json).unwrap()[..2], moves[..2]);
+        assert!(serde_json::from_str::<Move>("\"O-O\"").is_err());
+
+        assert_eq!(serde_json::to_string(&(File::E, Rank::R4)).unwrap(), r#"["e",4]"#);
+        assert_eq!(serde_json::to_string(&Piece { piece: PieceTypes::Queen, color: Color::White }).unwrap(), r#"{"piece":"Queen","color":"White"}"#);
+        assert_eq!(serde_json::to_string(&Game::new().castling).unwrap(), r#"{"white":["h","a"],"black":["h","a"]}"#);
+    }
+
+    #[test]
+    fn game_round_trip() {
+        let mut game = Game::new();
+        play(&mut game, "e4 e5 Nf3 Nc6 Bc4 Nf6 O-O").unwrap();
+
+        let json = serde_json::to_string(&game).unwrap();
+        assert!(json.starts_with(r#"{"initial_fen":"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1","moves":["e2e4","e7e5","g1f3","#));
+        assert!(json.ends_with(r#""e1g1"],"chess960":false,"fen":"r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4","game_status":"Ongoing","draw_offer":null,"result":null,"clock":null}"#));
+
+        let read: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.to_fen(), game.to_fen());
+        assert_eq!(read.moves, game.moves);
+        assert_eq!(read.history, game.history);
+
+        let board: Board = serde_json::from_str(&serde_json::to_string(&game.board).unwrap()).unwrap();
+        assert_eq!(fen::board_to_string(&board), fen::board_to_string(&game.board));
+
+        assert!(serde_json::from_str::<Game>(r#"{"initial_fen":"8/8/8/8/8/8/8/8 w - - 0 1","moves":[]}"#).is_err());
+        assert!(serde_json::from_str::<Game>(&json.replace("g1f3", "g1g3")).is_err());
+
+        //in Chess960 the king takes its own rook
+        let mut chess960 = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        chess960.chess960 = true;
+        chess960.try_move(Move::Castle { side: CastlingSide::KingSide, color: Color::White }).unwrap();
+        let json = serde_json::to_string(&chess960).unwrap();
+        assert!(json.contains(r#""moves":["e1h1"],"chess960":true"#));
+        assert_eq!(serde_json::from_str::<Game>(&json).unwrap().to_fen(), chess960.to_fen());
+
+        game.resign(Color::White).unwrap();
+        let json = serde_json::to_string(&game).unwrap();
+        assert!(json.ends_with(r#""game_status":{"Resigned":"Black"},"draw_offer":null,"result":{"winner":"Black","termination":"Resignation"},"clock":null}"#));
+        let read: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.result(), game.result());
+    }
+
+    #[test]
+    fn pending_promotion_and_clock_round_trip() {
+        let mut game = Game::from_fen("8/1P6/8/8/8/8/8/k6K w - - 0 1").unwrap();
+        game.set_clock(Clock::new(TimeControl::fischer(Duration::from_secs(300), Duration::from_secs(2))));
+        game.try_move("b7b8".parse().unwrap()).unwrap();
+        assert_eq!(game.game_status, GameStatus::Promoting);
+
+        let json = serde_json::to_string(&game).unwrap();
+        assert!(json.contains(r#""moves":["b7b8"],"chess960":false,"fen":null,"game_status":"Promoting""#));
+        assert!(json.contains(r#""clock":{"control":{"stages":[{"time":{"secs":300,"nanos":0},"moves":null,"bonus":{"Fischer":{"secs":2,"nanos":0}}}]}"#));
+
+        let mut read: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.game_status, GameStatus::Promoting);
+        let clock = read.clock.as_ref().unwrap();
+        assert_eq!(clock.running(), Some(Color::White));
+        assert!(clock.remaining(Color::White) > Duration::from_secs(299));
+        assert_eq!(clock.remaining(Color::Black), Duration::from_secs(300));
+
+        read.promote(PieceTypes::Queen).unwrap();
+        game.promote(PieceTypes::Queen).unwrap();
+        assert_eq!(read.to_fen(), "1Q6/8/8/8/8/8/8/k6K b - - 0 1");
+        assert_eq!(read.moves, game.moves);
+        assert_eq!(read.clock.as_ref().unwrap().running(), Some(Color::Black));
+
+        let json = serde_json::to_string(&game).unwrap();
+        assert!(json.contains(r#""moves":["b7b8q"]"#));
+        assert!(serde_json::from_str::<Game>(&json.replace(r#""stage":[0,0]"#, r#""stage":[1,0]"#)).is_err());
+    }
+}
@@ -0,0 +1,1265 @@
+/*!
+Probes Syzygy endgame tablebases. The tables are files named after their material, like KRPvKR.rtbw for win/draw/loss and KRPvKR.rtbz
+for the distance to zeroing, the number of plies until a capture or pawn move that keeps the result. A file is read the first time a
+position with its material is probed:
+```ignore
+let tablebase = Tablebase::open("syzygy")?;
+let wdl = tablebase.probe_wdl(&game)?;
+if let Some(best) = tablebase.best_move(&game)? {
+    game.try_move(best.mv)?;
+}
+```
+Positions with castling rights are not in the tables. The fifty move rule is not taken into account, a win that needs more than
+100 plies to the next zeroing move is reported as a CursedWin.
+*/
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::{Color, Game, Move, PieceTypes};
+use crate::bitboard::{self, Bitboard};
+
+///The result of a position for the player in turn with perfect play.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Wdl {
+    Loss,
+    ///a loss that is saved by the fifty move rule
+    BlessedLoss,
+    Draw,
+    ///a win that is spoiled by the fifty move rule
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            1 => Wdl::CursedWin,
+            2 => Wdl::Win,
+            _ => Wdl::Draw,
+        }
+    }
+
+    ///the result of a position from its distance to zeroing
+    fn from_dtz(dtz: i32) -> Wdl {
+        match dtz {
+            101.. => Wdl::CursedWin,
+            1..=100 => Wdl::Win,
+            0 => Wdl::Draw,
+            -100..=-1 => Wdl::Loss,
+            _ => Wdl::BlessedLoss,
+        }
+    }
+}
+
+///A legal move of a position with the result and the distance to zeroing after it, both for the player making the move.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TablebaseMove {
+    pub mv: Move,
+    pub wdl: Wdl,
+    pub dtz: i32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+enum Kind {
+    Wdl,
+    Dtz,
+}
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+const MAX_PIECES: usize = 7;
+
+///the flags of the pairs data of a table
+const STM: u8 = 1;
+const MAPPED: u8 = 2;
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+const WIDE: u8 = 16;
+const SINGLE_VALUE: u8 = 128;
+
+/**
+Binomial coefficients, BINOMIAL[k][n] is n choose k. Used to number the ways a group of equal pieces can stand on the free squares.
+*/
+const BINOMIAL: [[u64; 64]; MAX_PIECES] = binomial();
+///the a1-d1-d4 triangle numbered 0 to 9, below the diagonal first. Other squares are 0.
+const MAP_A1D1D4: [usize; 64] = map_a1d1d4();
+///the squares below the a1-h8 diagonal numbered 0 to 27
+const MAP_B1H1H7: [usize; 64] = map_b1h1h7();
+///the 462 legal placements of two kings with the first king in the a1-d1-d4 triangle
+const MAP_KK: [[u64; 64]; 10] = map_kk();
+///the pawn squares from 47 for a2 down to 0 for e7, higher for the files nearer the edge and the lower ranks
+const MAP_PAWNS: [usize; 64] = map_pawns();
+///the first index of the leading pawn on a square, indexed by the number of leading pawns and the square
+const LEAD_PAWN_IDX: [[u64; 64]; 6] = lead_pawns().0;
+///the number of indices of the leading pawns on a file, indexed by the number of leading pawns and the file
+const LEAD_PAWNS_SIZE: [[u64; 4]; 6] = lead_pawns().1;
+
+const fn binomial() -> [[u64; 64]; MAX_PIECES] {
+    let mut table = [[0; 64]; MAX_PIECES];
+    let mut n = 0;
+    while n < 64 {
+        table[0][n] = 1;
+        let mut k = 1;
+        while k < MAX_PIECES && k <= n {
+            table[k][n] = table[k - 1][n - 1] + table[k][n - 1];
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+}
+
+///the rank minus the file of a square, 0 on the a1-h8 diagonal and negative below it
+const fn off_diagonal(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+const fn map_a1d1d4() -> [usize; 64] {
+    let mut map = [0; 64];
+    let mut code = 0;
+    let mut square = 0;
+    while square <= 27 {
+        if off_diagonal(square) < 0 && square % 8 <= 3 {
+            map[square] = code;
+            code += 1;
+        }
+        square += 1;
+    }
+    square = 0;
+    while square <= 27 {
+        if off_diagonal(square) == 0 {
+            map[square] = code;
+            code += 1;
+        }
+        square += 1;
+    }
+    map
+}
+
+const fn map_b1h1h7() -> [usize; 64] {
+    let mut map = [0; 64];
+    let mut code = 0;
+    let mut square = 0;
+    while square < 64 {
+        if off_diagonal(square) < 0 {
+            map[square] = code;
+            code += 1;
+        }
+        square += 1;
+    }
+    map
+}
+
+const fn map_kk() -> [[u64; 64]; 10] {
+    let triangle = map_a1d1d4();
+    let mut map = [[0; 64]; 10];
+    let mut code = 0;
+
+    //placements with both kings on the diagonal are numbered last
+    let mut both_on_diagonal = false;
+    loop {
+        let mut idx = 0;
+        while idx < 10 {
+            let mut first = 0;
+            while first <= 27 {
+                if triangle[first] == idx && (idx > 0 || first == 1) {
+                    let mut second = 0;
+                    while second < 64 {
+                        let near = (first % 8).abs_diff(second % 8) <= 1 && (first / 8).abs_diff(second / 8) <= 1;
+                        let diagonal = off_diagonal(first) == 0;
+                        if !(near || diagonal && off_diagonal(second) > 0) && (diagonal && off_diagonal(second) == 0) == both_on_diagonal {
+                            map[idx][second] = code;
+                            code += 1;
+                        }
+                        second += 1;
+                    }
+                }
+                first += 1;
+            }
+            idx += 1;
+        }
+        if both_on_diagonal {
+            break;
+        }
+        both_on_diagonal = true;
+    }
+    map
+}
+
+const fn map_pawns() -> [usize; 64] {
+    let mut map = [0; 64];
+    let mut available: usize = 47;
+    let mut file = 0;
+    while file < 4 {
+        let mut rank = 1;
+        while rank < 7 {
+            map[rank * 8 + file] = available;
+            map[rank * 8 + 7 - file] = available - 1;
+            available = available.saturating_sub(2);
+            rank += 1;
+        }
+        file += 1;
+    }
+    map
+}
+
+const fn lead_pawns() -> ([[u64; 64]; 6], [[u64; 4]; 6]) {
+    let binomial = binomial();
+    let map = map_pawns();
+    let mut idx_table = [[0; 64]; 6];
+    let mut size_table = [[0; 4]; 6];
+    let mut count = 1;
+    while count < 6 {
+        let mut file = 0;
+        while file < 4 {
+            let mut idx = 0;
+            let mut rank = 1;
+            while rank < 7 {
+                let square = rank * 8 + file;
+                idx_table[count][square] = idx;
+                idx += binomial[count - 1][map[square]];
+                rank += 1;
+            }
+            size_table[count][file] = idx;
+            file += 1;
+        }
+        count += 1;
+    }
+    (idx_table, size_table)
+}
+
+///the code of a piece in the tables, the piece type from 1 for pawns to 6 for kings with 8 added for black
+fn piece_code(piece: PieceTypes, color: Color) -> u8 {
+    let piece = match piece {
+        PieceTypes::Pawn(_) => 1,
+        PieceTypes::Knight => 2,
+        PieceTypes::Bishop => 3,
+        PieceTypes::Rook => 4,
+        PieceTypes::Queen => 5,
+        PieceTypes::King => 6,
+    };
+    match color {
+        Color::White => piece,
+        Color::Black => piece | 8,
+    }
+}
+
+///The pairs data of one side of one file of a table: how the positions are indexed and where their compressed values are.
+#[derive(Debug, Default, Clone)]
+struct Pairs {
+    flags: u8,
+    ///the pieces in the order they are indexed
+    pieces: [u8; MAX_PIECES],
+    ///the sizes of the groups of pieces that are indexed together, ending with 0
+    group_len: [usize; MAX_PIECES + 1],
+    ///the factor of the index of every group. The entry after the last group is the number of indices.
+    group_idx: [u64; MAX_PIECES + 1],
+    single_value: u16,
+    block_size: usize,
+    span: u64,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_lengths: usize,
+    block_lengths_size: usize,
+    blocks: usize,
+    min_sym_len: u32,
+    lowest_sym: usize,
+    base: Vec<u64>,
+    sym_len: Vec<u8>,
+    tree: usize,
+    data: usize,
+    ///where the four maps of a DTZ table start, in entries from the start of the maps
+    map_idx: [usize; 4],
+}
+
+///A table read from its file. All the offsets of the pairs data point into bytes.
+struct Table {
+    bytes: Vec<u8>,
+    symmetric: bool,
+    has_pawns: bool,
+    ///both colors have pawns, the pawns of the other color are then the second group
+    both_pawns: bool,
+    ///indexed by file, only one for tables without pawns, and then by side
+    pairs: Vec<Vec<Pairs>>,
+    ///the start of the DTZ maps
+    map: usize,
+}
+
+///bounds checked reads from the bytes of a table
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn u8(&self, at: usize) -> Result<u8, ()> {
+        self.0.get(at).copied().ok_or(())
+    }
+
+    fn u16(&self, at: usize) -> Result<u16, ()> {
+        Ok(u16::from_le_bytes(self.0.get(at..at + 2).ok_or(())?.try_into().unwrap()))
+    }
+
+    fn u32(&self, at: usize) -> Result<u32, ()> {
+        Ok(u32::from_le_bytes(self.0.get(at..at + 4).ok_or(())?.try_into().unwrap()))
+    }
+
+    fn u32_be(&self, at: usize) -> Result<u32, ()> {
+        Ok(u32::from_be_bytes(self.0.get(at..at + 4).ok_or(())?.try_into().unwrap()))
+    }
+
+    fn u64_be(&self, at: usize) -> Result<u64, ()> {
+        Ok(u64::from_be_bytes(self.0.get(at..at + 8).ok_or(())?.try_into().unwrap()))
+    }
+}
+
+///the number of every piece of white and black in a table name like KRPvKR, indexed by color and piece code
+fn parse_material(name: &str) -> Option<[[u8; 7]; 2]> {
+    let (white, black) = name.split_once('v')?;
+    let mut material = [[0; 7]; 2];
+    for (side, pieces) in [white, black].into_iter().enumerate() {
+        for c in pieces.chars() {
+            let code = match c {
+                'P' => 1,
+                'N' => 2,
+                'B' => 3,
+                'R' => 4,
+                'Q' => 5,
+                'K' => 6,
+                _ => return None,
+            };
+            material[side][code] += 1;
+        }
+    }
+    Some(material)
+}
+
+///the name of the table of the position with the pieces of first before the v, like KRPvKR
+fn material_name(game: &Game, first: Color) -> String {
+    let side = |color| {
+        [(PieceTypes::King, 'K'), (PieceTypes::Queen, 'Q'), (PieceTypes::Rook, 'R'), (PieceTypes::Bishop, 'B'), (PieceTypes::Knight, 'N'), (PieceTypes::Pawn(false), 'P')]
+            .iter()
+            .map(|(piece, c)| c.to_string().repeat(game.board.pieces(*piece, color).count_ones() as usize))
+            .collect::<String>()
+    };
+    format!("{}v{}", side(first), side(first.opposite()))
+}
+
+impl Table {
+    fn read(bytes: Vec<u8>, name: &str, kind: Kind) -> Result<Table, ()> {
+        let material = parse_material(name).ok_or(())?;
+        let magic = match kind {
+            Kind::Wdl => WDL_MAGIC,
+            Kind::Dtz => DTZ_MAGIC,
+        };
+        if bytes.get(0..4) != Some(&magic[..]) {
+            return Err(());
+        }
+
+        let reader = Reader(&bytes);
+        let flags = reader.u8(4)?;
+        let has_pawns = flags & 2 != 0;
+        if has_pawns != (material[0][1] + material[1][1] > 0) {
+            return Err(());
+        }
+        let symmetric = material[0] == material[1];
+        let unique = material.iter().any(|side| side[1..6].contains(&1));
+        let both_pawns = material[0][1] > 0 && material[1][1] > 0;
+        let piece_count: usize = material.iter().flatten().map(|n| *n as usize).sum();
+        if piece_count > MAX_PIECES {
+            return Err(());
+        }
+
+        let sides = if kind == Kind::Wdl && flags & 1 != 0 { 2 } else { 1 };
+        let files = if has_pawns { 4 } else { 1 };
+        let mut pairs = vec![vec![Pairs::default(); sides]; files];
+
+        let mut at = 5;
+        for (file, file_pairs) in pairs.iter_mut().enumerate() {
+            let order = [
+                [reader.u8(at)? & 0xf, if both_pawns { reader.u8(at + 1)? & 0xf } else { 0xf }],
+                [reader.u8(at)? >> 4, if both_pawns { reader.u8(at + 1)? >> 4 } else { 0xf }],
+            ];
+            at += 1 + both_pawns as usize;
+            for k in 0..piece_count {
+                let byte = reader.u8(at + k)?;
+                for (side, pairs) in file_pairs.iter_mut().enumerate() {
+                    pairs.pieces[k] = if side == 0 { byte & 0xf } else { byte >> 4 };
+                }
+            }
+            at += piece_count;
+            for (side, pairs) in file_pairs.iter_mut().enumerate() {
+                pairs.set_groups(piece_count, has_pawns, unique, both_pawns, order[side], file);
+            }
+        }
+        at += at & 1;
+
+        for pairs in pairs.iter_mut().flatten() {
+            at = pairs.set_sizes(&reader, at)?;
+        }
+
+        let map = at;
+        if kind == Kind::Dtz {
+            for file_pairs in &mut pairs {
+                let pairs = &mut file_pairs[0];
+                if pairs.flags & MAPPED == 0 {
+                    continue;
+                }
+                if pairs.flags & WIDE != 0 {
+                    at += at & 1;
+                    for i in 0..4 {
+                        pairs.map_idx[i] = (at - map) / 2 + 1;
+                        at += 2 + 2 * reader.u16(at)? as usize;
+                    }
+                }
+                else {
+                    for i in 0..4 {
+                        pairs.map_idx[i] = at - map + 1;
+                        at += 1 + reader.u8(at)? as usize;
+                    }
+                }
+            }
+            at += at & 1;
+        }
+
+        for pairs in pairs.iter_mut().flatten() {
+            pairs.sparse_index = at;
+            at += pairs.sparse_index_size * 6;
+        }
+        for pairs in pairs.iter_mut().flatten() {
+            pairs.block_lengths = at;
+            at += pairs.block_lengths_size * 2;
+        }
+        for pairs in pairs.iter_mut().flatten() {
+            at = (at + 0x3f) & !0x3f;
+            pairs.data = at;
+            at += pairs.blocks * pairs.block_size;
+            if pairs.blocks > 0 && at > bytes.len() {
+                return Err(());
+            }
+        }
+
+        Ok(Table { bytes, symmetric, has_pawns, both_pawns, pairs, map })
+    }
+}
+
+impl Pairs {
+    /**
+    Splits the pieces into the groups that are indexed together: the leading pawns or the first two or three pieces, the pawns of the other
+    color and then every run of equal pieces. order tells where the leading group and the other pawns come in the index.
+    */
+    fn set_groups(&mut self, piece_count: usize, has_pawns: bool, unique: bool, both_pawns: bool, order: [u8; 2], file: usize) {
+        let mut n = 0;
+        let mut first_len: i32 = if has_pawns { 0 } else if unique { 3 } else { 2 };
+        self.group_len[0] = 1;
+        for i in 1..piece_count {
+            first_len -= 1;
+            if first_len > 0 || self.pieces[i] == self.pieces[i - 1] {
+                self.group_len[n] += 1;
+            }
+            else {
+                n += 1;
+                self.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        self.group_len[n] = 0;
+
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - self.group_len[0] - if both_pawns { self.group_len[1] } else { 0 };
+        let mut idx = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                self.group_idx[0] = idx;
+                idx *= match (has_pawns, unique) {
+                    (true, _) => LEAD_PAWNS_SIZE[self.group_len[0]][file],
+                    (false, true) => 31332,
+                    (false, false) => 462,
+                };
+            }
+            else if k == order[1] as usize {
+                self.group_idx[1] = idx;
+                idx *= BINOMIAL[self.group_len[1]][48 - self.group_len[0]];
+            }
+            else {
+                self.group_idx[next] = idx;
+                idx *= BINOMIAL[self.group_len[next]][free_squares];
+                free_squares -= self.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        self.group_idx[n] = idx;
+    }
+
+    ///reads the header of the compressed values starting at at and returns where the next header starts
+    fn set_sizes(&mut self, reader: &Reader, at: usize) -> Result<usize, ()> {
+        self.flags = reader.u8(at)?;
+        if self.flags & SINGLE_VALUE != 0 {
+            self.single_value = reader.u8(at + 1)? as u16;
+            return Ok(at + 2);
+        }
+
+        let size = self.group_idx[self.group_len.iter().position(|len| *len == 0).unwrap()];
+        self.block_size = 1 << reader.u8(at + 1)?;
+        self.span = 1 << reader.u8(at + 2)?;
+        self.sparse_index_size = size.div_ceil(self.span) as usize;
+        let padding = reader.u8(at + 3)? as usize;
+        self.blocks = reader.u32(at + 4)? as usize;
+        self.block_lengths_size = self.blocks + padding;
+
+        let max_sym_len = reader.u8(at + 8)? as u32;
+        self.min_sym_len = reader.u8(at + 9)? as u32;
+        if max_sym_len < self.min_sym_len || max_sym_len > 64 {
+            return Err(());
+        }
+        self.lowest_sym = at + 10;
+
+        //canonical Huffman codes: longer codes have lower values, base[i] is the lowest code of length min_sym_len + i padded to 64 bits
+        let lengths = (max_sym_len - self.min_sym_len + 1) as usize;
+        let mut base = vec![0u64; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = reader.u16(self.lowest_sym + 2 * i)? as u64;
+            let next = reader.u16(self.lowest_sym + 2 * (i + 1))? as u64;
+            base[i] = (base[i + 1] + lowest).checked_sub(next).ok_or(())? / 2;
+        }
+        for (i, base) in base.iter_mut().enumerate() {
+            *base = base.checked_shl(64 - i as u32 - self.min_sym_len).unwrap_or(0);
+        }
+        self.base = base;
+
+        let at = self.lowest_sym + 2 * lengths;
+        let symbols = reader.u16(at)? as usize;
+        self.tree = at + 2;
+        if self.tree + 3 * symbols > reader.0.len() {
+            return Err(());
+        }
+
+        //every symbol is a value or a pair of two symbols, sym_len is the number of values of a symbol minus one
+        self.sym_len = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols {
+            self.set_sym_len(reader, symbol, &mut visited)?;
+        }
+
+        Ok(self.tree + 3 * symbols + (symbols & 1))
+    }
+
+    fn set_sym_len(&mut self, reader: &Reader, symbol: usize, visited: &mut [bool]) -> Result<(), ()> {
+        if visited[symbol] {
+            return Ok(());
+        }
+        visited[symbol] = true;
+
+        let (left, right) = self.children(reader, symbol)?;
+        if right == 0xfff {
+            return Ok(());
+        }
+        if left >= self.sym_len.len() || right >= self.sym_len.len() {
+            return Err(());
+        }
+        self.set_sym_len(reader, left, visited)?;
+        self.set_sym_len(reader, right, visited)?;
+        self.sym_len[symbol] = self.sym_len[left].checked_add(self.sym_len[right]).and_then(|len| len.checked_add(1)).ok_or(())?;
+        Ok(())
+    }
+
+    ///the two symbols a symbol is made of. A value has 0xfff as its right symbol and the value as its left.
+    fn children(&self, reader: &Reader, symbol: usize) -> Result<(usize, usize), ()> {
+        let at = self.tree + 3 * symbol;
+        let (a, b, c) = (reader.u8(at)? as usize, reader.u8(at + 1)? as usize, reader.u8(at + 2)? as usize);
+        Ok(((b & 0xf) << 8 | a, c << 4 | b >> 4))
+    }
+
+    ///the value stored at idx
+    fn decompress(&self, reader: &Reader, idx: u64) -> Result<u16, ()> {
+        if self.flags & SINGLE_VALUE != 0 {
+            return Ok(self.single_value);
+        }
+
+        //the sparse index points to the block and offset of every span:th value, from there the block lengths are walked to idx
+        let k = (idx / self.span) as usize;
+        if k >= self.sparse_index_size {
+            return Err(());
+        }
+        let mut block = reader.u32(self.sparse_index + 6 * k)? as usize;
+        let mut offset = reader.u16(self.sparse_index + 6 * k + 4)? as i64;
+        offset += (idx % self.span) as i64 - (self.span / 2) as i64;
+
+        let block_length = |block: usize| -> Result<i64, ()> {
+            if block >= self.block_lengths_size {
+                return Err(());
+            }
+            Ok(reader.u16(self.block_lengths + 2 * block)? as i64)
+        };
+        while offset < 0 {
+            block = block.checked_sub(1).ok_or(())?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        let mut at = self.data + block * self.block_size;
+        let mut buffer = reader.u64_be(at)?;
+        at += 8;
+        let mut buffer_size = 64;
+        let mut symbol;
+        loop {
+            let mut len = 0;
+            while buffer < self.base[len] {
+                len += 1;
+                if len == self.base.len() {
+                    return Err(());
+                }
+            }
+            let shift = 64 - len as u32 - self.min_sym_len;
+            symbol = ((buffer - self.base[len]).checked_shr(shift).unwrap_or(0)) as usize + reader.u16(self.lowest_sym + 2 * len)? as usize;
+            let values = *self.sym_len.get(symbol).ok_or(())? as i64 + 1;
+            if offset < values {
+                break;
+            }
+            offset -= values;
+
+            let len = len as u32 + self.min_sym_len;
+            buffer = buffer.checked_shl(len).unwrap_or(0);
+            buffer_size -= len as i32;
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (reader.u32_be(at)? as u64) << (64 - buffer_size);
+                at += 4;
+            }
+        }
+
+        //the symbol stands for sym_len + 1 values in a row, walk down the pairs to the one at offset
+        while self.sym_len[symbol] != 0 {
+            let (left, right) = self.children(reader, symbol)?;
+            let values = self.sym_len[left] as i64 + 1;
+            if offset < values {
+                symbol = left;
+            }
+            else {
+                offset -= values;
+                symbol = right;
+            }
+        }
+        Ok(self.children(reader, symbol)?.0 as u16)
+    }
+}
+
+///the index of a position in a table, squares are ordered like the pieces of pairs with the lead_pawns leading pawns first
+fn encode(pairs: &Pairs, squares: &mut [usize], lead_pawns: usize, has_pawns: bool, remaining_pawns: bool) -> u64 {
+    if squares[0] % 8 > 3 {
+        for square in squares.iter_mut() {
+            *square ^= 7;
+        }
+    }
+
+    let mut idx;
+    if has_pawns {
+        idx = LEAD_PAWN_IDX[lead_pawns][squares[0]];
+        squares[1..lead_pawns].sort_by_key(|square| MAP_PAWNS[*square]);
+        for (i, square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+            idx += BINOMIAL[i][MAP_PAWNS[*square]];
+        }
+    }
+    else {
+        if squares[0] / 8 > 3 {
+            for square in squares.iter_mut() {
+                *square ^= 56;
+            }
+        }
+        //the first leading piece off the diagonal is put below it
+        for i in 0..pairs.group_len[0] {
+            match off_diagonal(squares[i]) {
+                0 => continue,
+                off if off > 0 => {
+                    for square in squares[i..].iter_mut() {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                },
+                _ => {},
+            }
+            break;
+        }
+
+        let rank = |square: usize| square as u64 / 8;
+        if pairs.group_len[0] == 3 {
+            let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+            let adjust1 = (s1 > s0) as u64;
+            let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+            idx = if off_diagonal(s0) != 0 {
+                (MAP_A1D1D4[s0] as u64 * 63 + (s1 as u64 - adjust1)) * 62 + s2 as u64 - adjust2
+            }
+            else if off_diagonal(s1) != 0 {
+                (6 * 63 + rank(s0) * 28 + MAP_B1H1H7[s1] as u64) * 62 + s2 as u64 - adjust2
+            }
+            else if off_diagonal(s2) != 0 {
+                6 * 63 * 62 + 4 * 28 * 62 + rank(s0) * 7 * 28 + (rank(s1) - adjust1) * 28 + MAP_B1H1H7[s2] as u64
+            }
+            else {
+                6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank(s0) * 7 * 6 + (rank(s1) - adjust1) * 6 + (rank(s2) - adjust2)
+            };
+        }
+        else {
+            idx = MAP_KK[MAP_A1D1D4[squares[0]]][squares[1]];
+        }
+    }
+    idx *= pairs.group_idx[0];
+
+    //the other groups are numbered by their squares among the squares not taken by the groups before them
+    let mut remaining_pawns = remaining_pawns;
+    let mut start = pairs.group_len[0];
+    let mut next = 1;
+    while pairs.group_len[next] != 0 {
+        let len = pairs.group_len[next];
+        squares[start..start + len].sort_unstable();
+        let mut n = 0;
+        for i in 0..len {
+            let square = squares[start + i];
+            let adjust = squares[..start].iter().filter(|s| square > **s).count();
+            n += BINOMIAL[i + 1][square - adjust - if remaining_pawns { 8 } else { 0 }];
+        }
+        remaining_pawns = false;
+        idx += n * pairs.group_idx[next];
+        start += len;
+        next += 1;
+    }
+    idx
+}
+
+///A set of Syzygy tables found in one or more directories.
+#[derive(Default)]
+pub struct Tablebase {
+    paths: HashMap<(String, Kind), PathBuf>,
+    tables: Mutex<HashMap<(String, Kind), Arc<Table>>>,
+}
+
+impl Tablebase {
+    ///the tables in the directory at path
+    pub fn open(path: impl AsRef<Path>) -> Result<Tablebase, TablebaseError> {
+        let mut tablebase = Tablebase::default();
+        tablebase.add_directory(path)?;
+        Ok(tablebase)
+    }
+
+    ///adds the tables in the directory at path and returns how many files were found
+    pub fn add_directory(&mut self, path: impl AsRef<Path>) -> Result<usize, TablebaseError> {
+        let mut found = 0;
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            let kind = match path.extension().and_then(|e| e.to_str()) {
+                Some("rtbw") => Kind::Wdl,
+                Some("rtbz") => Kind::Dtz,
+                _ => continue,
+            };
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+            if parse_material(name).is_some() {
+                self.paths.insert((name.to_string(), kind), path.clone());
+                found += 1;
+            }
+        }
+        Ok(found)
+    }
+
+    ///the most pieces, kings included, of the tables found. Positions with more pieces can not be probed.
+    pub fn max_pieces(&self) -> usize {
+        self.paths.keys().map(|(name, _)| name.len() - 1).max().unwrap_or(0)
+    }
+
+    ///the result of the position for the player in turn
+    pub fn probe_wdl(&self, game: &Game) -> Result<Wdl, TablebaseError> {
+        let mut game = self.prepare(game)?;
+        Ok(Wdl::from_value(self.search(&mut game, false)?.0))
+    }
+
+    /**
+    The distance to zeroing of the position in plies, positive when the player in turn wins and negative when it loses. A distance above 100
+    is a cursed win and below -100 a blessed loss. A draw is 0 and a checkmated player has -1. The distance can be one ply longer than the
+    shortest for some positions since the tables store some values in full moves.
+    */
+    pub fn probe_dtz(&self, game: &Game) -> Result<i32, TablebaseError> {
+        let mut game = self.prepare(game)?;
+        self.dtz(&mut game)
+    }
+
+    ///every legal move of the position with the result after it, the best first: the fastest wins, then draws and the slowest losses
+    pub fn moves(&self, game: &Game) -> Result<Vec<TablebaseMove>, TablebaseError> {
+        let mut game = self.prepare(game)?;
+        let mut moves = vec![];
+
+        for mv in game.legal_moves() {
+            let zeroing = zeroing(&game, mv);
+            game.make_move(mv);
+            game.turn = game.turn.opposite();
+
+            let mut dtz = match zeroing {
+                true => dtz_before_zeroing(-self.search(&mut game, false)?.0),
+                false => {
+                    let dtz = -self.dtz(&mut game)?;
+                    dtz + dtz.signum()
+                },
+            };
+            //a checkmate is a win in one ply even though the tables count the reply
+            if dtz == 2 && game.in_check(game.turn) && game.legal_moves().is_empty() {
+                dtz = 1;
+            }
+            game.unmake_move();
+
+            moves.push(TablebaseMove { mv, wdl: Wdl::from_dtz(dtz), dtz });
+        }
+
+        moves.sort_by_key(|m| (std::cmp::Reverse(m.wdl), m.dtz));
+        Ok(moves)
+    }
+
+    ///the best move of the position, see moves. None if there are no legal moves.
+    pub fn best_move(&self, game: &Game) -> Result<Option<TablebaseMove>, TablebaseError> {
+        Ok(self.moves(game)?.into_iter().next())
+    }
+
+    fn prepare(&self, game: &Game) -> Result<Game, TablebaseError> {
+        if [Color::White, Color::Black].iter().any(|color| game.castling.rights(*color) != (None, None)) {
+            return Err(TablebaseError::Castling);
+        }
+        let mut game = game.clone();
+        game.clock = None;
+        Ok(game)
+    }
+
+    /**
+    The result of the position with the captures searched since the tables do not store the right value when a capture is the only good move.
+    With zeroing_pawns the pawn moves are searched too. The flag is true when the best move is a capture or pawn move.
+    */
+    fn search(&self, game: &mut Game, zeroing_pawns: bool) -> Result<(i32, bool), TablebaseError> {
+        let moves = game.legal_moves();
+        let mut best = -2;
+        let mut searched = 0;
+
+        for &mv in &moves {
+            if !(capture(game, mv) || zeroing_pawns && pawn_move(game, mv)) {
+                continue;
+            }
+            searched += 1;
+
+            game.make_move(mv);
+            game.turn = game.turn.opposite();
+            let value = -self.search(game, false)?.0;
+            game.unmake_move();
+
+            if value > best {
+                best = value;
+                if value == 2 {
+                    return Ok((value, true));
+                }
+            }
+        }
+
+        //the table is not needed when every move was searched, which also covers en passant that the tables leave out
+        let no_more_moves = searched > 0 && searched == moves.len();
+        let value = match no_more_moves {
+            true => best,
+            false => self.probe_table(game, Kind::Wdl, 0)?.ok_or(TablebaseError::InvalidTable(material_name(game, Color::White)))?,
+        };
+
+        match best >= value {
+            true => Ok((best, best > 0 || no_more_moves)),
+            false => Ok((value, false)),
+        }
+    }
+
+    fn dtz(&self, game: &mut Game) -> Result<i32, TablebaseError> {
+        let (wdl, zeroing_best) = self.search(game, true)?;
+        if wdl == 0 {
+            return Ok(0);
+        }
+        if zeroing_best {
+            return Ok(dtz_before_zeroing(wdl));
+        }
+
+        if let Some(dtz) = self.probe_table(game, Kind::Dtz, wdl)? {
+            let cursed = if wdl.abs() == 1 { 100 } else { 0 };
+            return Ok((dtz + cursed) * wdl.signum());
+        }
+
+        //the table only has the other side in turn so the moves are searched one ply deep
+        let mut best = i32::MAX;
+        for mv in game.legal_moves() {
+            let zeroing = zeroing(game, mv);
+            game.make_move(mv);
+            game.turn = game.turn.opposite();
+
+            let mut dtz = match zeroing {
+                true => -dtz_before_zeroing(self.search(game, false)?.0),
+                false => -self.dtz(game)?,
+            };
+            if dtz == 1 && game.in_check(game.turn) && game.legal_moves().is_empty() {
+                best = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < best && dtz.signum() == wdl.signum() {
+                best = dtz;
+            }
+            game.unmake_move();
+        }
+
+        Ok(if best == i32::MAX { -1 } else { best })
+    }
+
+    ///the table of the material of the game and whether its colors are swapped in the table
+    fn table(&self, game: &Game, kind: Kind) -> Result<(Arc<Table>, bool), TablebaseError> {
+        let white = material_name(game, Color::White);
+        let black = material_name(game, Color::Black);
+        let (name, swapped) = match self.paths.contains_key(&(white.clone(), kind)) {
+            true => (white, false),
+            false if self.paths.contains_key(&(black.clone(), kind)) => (black, true),
+            false => return Err(TablebaseError::MissingTable(white)),
+        };
+
+        let mut tables = self.tables.lock().unwrap();
+        if let Some(table) = tables.get(&(name.clone(), kind)) {
+            return Ok((Arc::clone(table), swapped));
+        }
+
+        let bytes = fs::read(&self.paths[&(name.clone(), kind)])?;
+        let table = Arc::new(Table::read(bytes, &name, kind).map_err(|_| TablebaseError::InvalidTable(name.clone()))?);
+        tables.insert((name, kind), Arc::clone(&table));
+        Ok((table, swapped))
+    }
+
+    ///the value of the position in the table, the result for WDL and the distance for DTZ. None if the DTZ table only has the other side in turn.
+    fn probe_table(&self, game: &Game, kind: Kind, wdl: i32) -> Result<Option<i32>, TablebaseError> {
+        let occupied = game.board.occupied();
+        if occupied.count_ones() == 2 {
+            return Ok(Some(0));
+        }
+
+        let (table, swapped) = self.table(game, kind)?;
+        let invalid = || TablebaseError::InvalidTable(material_name(game, Color::White));
+
+        //tables are stored with white as the stronger side and for symmetric material with white in turn
+        let black_to_move = game.turn == Color::Black;
+        let flip = swapped || (table.symmetric && black_to_move);
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let side = (flip ^ black_to_move) as usize;
+
+        let mut squares = [0; MAX_PIECES];
+        let mut pieces = [0; MAX_PIECES];
+        let mut size = 0;
+        let mut lead: Bitboard = 0;
+        let mut file = 0;
+
+        if table.has_pawns {
+            let code = table.pairs[0][0].pieces[0] ^ flip_color;
+            let color = if code & 8 != 0 { Color::Black } else { Color::White };
+            lead = game.board.pieces(PieceTypes::Pawn(false), color);
+            for square in bitboard::squares(lead) {
+                *squares.get_mut(size).ok_or_else(invalid)? = bitboard::index(square) ^ flip_squares;
+                size += 1;
+            }
+            let first = (0..size).max_by_key(|i| MAP_PAWNS[squares[*i]]).ok_or_else(invalid)?;
+            squares.swap(0, first);
+            file = (squares[0] % 8).min(7 - squares[0] % 8);
+        }
+        let lead_pawns = size;
+
+        let file_pairs = &table.pairs[file];
+        let pairs = &file_pairs[side % file_pairs.len()];
+        if kind == Kind::Dtz && (pairs.flags & STM) as usize != side && (table.has_pawns || !table.symmetric) {
+            return Ok(None);
+        }
+
+        for square in bitboard::squares(occupied & !lead) {
+            let piece = game.board[square].ok_or_else(invalid)?;
+            *squares.get_mut(size).ok_or_else(invalid)? = bitboard::index(square) ^ flip_squares;
+            pieces[size] = piece_code(piece.piece, piece.color) ^ flip_color;
+            size += 1;
+        }
+
+        //the pieces are put in the order of the table
+        for i in lead_pawns..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|j| pieces[*j] == pairs.pieces[i]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        let idx = encode(pairs, &mut squares[..size], lead_pawns, table.has_pawns, table.both_pawns);
+        let reader = Reader(&table.bytes);
+        let value = pairs.decompress(&reader, idx).map_err(|_| invalid())? as i32;
+
+        match kind {
+            Kind::Wdl => Ok(Some(value - 2)),
+            Kind::Dtz => Ok(Some(map_score(&table, pairs, value, wdl).map_err(|_| invalid())?)),
+        }
+    }
+}
+
+///the distance of a DTZ table value. Values can be mapped and stored in full moves instead of plies.
+fn map_score(table: &Table, pairs: &Pairs, value: i32, wdl: i32) -> Result<i32, ()> {
+    const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+
+    let mut value = value;
+    if pairs.flags & MAPPED != 0 {
+        let reader = Reader(&table.bytes);
+        let idx = pairs.map_idx[WDL_MAP[(wdl + 2) as usize]] + value as usize;
+        value = match pairs.flags & WIDE != 0 {
+            true => reader.u16(table.map + 2 * idx)? as i32,
+            false => reader.u8(table.map + idx)? as i32,
+        };
+    }
+
+    let plies = match wdl {
+        2 => pairs.flags & WIN_PLIES != 0,
+        -2 => pairs.flags & LOSS_PLIES != 0,
+        _ => false,
+    };
+    if !plies {
+        value *= 2;
+    }
+    Ok(value + 1)
+}
+
+///the distance to zeroing of a position where the best move is a capture or pawn move
+fn dtz_before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        2 => 1,
+        1 => 101,
+        -1 => -101,
+        -2 => -1,
+        _ => 0,
+    }
+}
+
+fn capture(game: &Game, mv: Move) -> bool {
+    match mv {
+        Move::Normal { from, to } | Move::Promotion { from, to, .. } => game.board[to].is_some() || game.en_passant_capture(from, to).is_some(),
+        Move::Castle { .. } => false,
+    }
+}
+
+fn pawn_move(game: &Game, mv: Move) -> bool {
+    match mv {
+        Move::Normal { from, .. } | Move::Promotion { from, .. } => matches!(game.board[from], Some(piece) if matches!(piece.piece, PieceTypes::Pawn(_))),
+        Move::Castle { .. } => false,
+    }
+}
+
+///captures and pawn moves reset the fifty move counter
+fn zeroing(game: &Game, mv: Move) -> bool {
+    capture(game, mv) || pawn_move(game, mv)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TablebaseError {
+    Io(io::ErrorKind),
+    ///there is no table for the material, named like KRvK
+    MissingTable(String),
+    ///the file of the table could not be read as a Syzygy table
+    InvalidTable(String),
+    Castling,
+}
+
+impl From<io::Error> for TablebaseError {
+    fn from(error: io::Error) -> Self {
+        TablebaseError::Io(error.kind())
+    }
+}
+
+impl fmt::Display for TablebaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TablebaseError::Io(kind) => write!(f, "Io: could not read the tablebase: {}", kind),
+            TablebaseError::MissingTable(name) => write!(f, "Missing table: there is no table for {}", name),
+            TablebaseError::InvalidTable(name) => write!(f, "Invalid table: the table for {} is not a valid Syzygy table", name),
+            TablebaseError::Castling => write!(f, "Castling: positions with castling rights are not in the tablebase"),
+        }
+    }
+}
+
+impl error::Error for TablebaseError { }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GameStatus;
+
+    ///a table where every position of each side has the same value, the smallest table the format allows
+    fn single_value_table(kind: Kind, pieces: &[u8], values: &[u8]) -> Vec<u8> {
+        let mut bytes = match kind {
+            Kind::Wdl => WDL_MAGIC.to_vec(),
+            Kind::Dtz => DTZ_MAGIC.to_vec(),
+        };
+        bytes.push((values.len() == 2) as u8);
+        bytes.push(0);
+        bytes.extend(pieces.iter().map(|piece| piece | piece << 4));
+        if bytes.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        for value in values {
+            bytes.extend([SINGLE_VALUE, *value]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn index_tables() {
+        assert_eq!(MAP_KK.iter().flatten().max(), Some(&461));
+        assert_eq!(MAP_A1D1D4[1], 0);
+        assert_eq!(MAP_A1D1D4[27], 9);
+        assert_eq!(MAP_B1H1H7[55], 27);
+        assert_eq!((MAP_PAWNS[8], MAP_PAWNS[15], MAP_PAWNS[52]), (47, 46, 0));
+        assert_eq!(LEAD_PAWNS_SIZE[1], [6, 6, 6, 6]);
+        assert_eq!(BINOMIAL[2][62], 1891);
+
+        //every placement of KRvK, with the kings apart, gets an index inside the 31332 of the leading group
+        let mut pairs = Pairs::default();
+        pairs.pieces[..3].copy_from_slice(&[6, 4, 14]);
+        pairs.set_groups(3, false, true, false, [0, 0xf], 0);
+        assert_eq!((pairs.group_len[0], pairs.group_idx[1]), (3, 31332));
+        for (king, rook, other) in (0..64).flat_map(|k: usize| (0..64).flat_map(move |r| (0..64).map(move |o| (k, r, o)))) {
+            if king == rook || rook == other || (king % 8).abs_diff(other % 8) <= 1 && (king / 8).abs_diff(other / 8) <= 1 {
+                continue;
+            }
+            assert!(encode(&pairs, &mut [king, rook, other], 0, false, false) < 31332);
+        }
+    }
+
+    #[test]
+    fn huffman_blocks() {
+        //three values coded with 1 and 2 bits: 1 is value 7 and 00, 01 are the pairs (7, 7) and (3, 7)
+        let mut bytes = vec![0; 10];
+        bytes[1] = 6; //64 byte blocks
+        bytes[2] = 2; //a sparse index entry every 4 values
+        bytes[4] = 2; //two blocks
+        bytes[8] = 2;
+        bytes[9] = 1;
+        bytes.extend([2, 0, 0, 0]); //the lowest symbols of length 1 and 2
+        bytes.extend([5, 0]);
+        bytes.extend([0x02, 0x20, 0x00, 0x03, 0x20, 0x00, 0x07, 0xf0, 0xff, 0x03, 0xf0, 0xff, 0x00, 0xf0, 0xff]);
+        bytes.push(0);
+
+        let mut pairs = Pairs { group_len: [1, 0, 0, 0, 0, 0, 0, 0], group_idx: [1, 7, 0, 0, 0, 0, 0, 0], ..Default::default() };
+        let end = pairs.set_sizes(&Reader(&bytes), 0).unwrap();
+        assert_eq!(end, bytes.len());
+        assert_eq!(pairs.sym_len, [1, 1, 0, 0, 0]);
+
+        //block 0 holds 1, 01, 00 = 7, 3 7, 7 7 and block 1 holds 01 = 3 7. The sparse index points at the values 2 and 6.
+        pairs.sparse_index = bytes.len();
+        bytes.extend([0, 0, 0, 0, 2, 0, 1, 0, 0, 0, 1, 0]);
+        pairs.block_lengths = bytes.len();
+        bytes.extend([4, 0, 1, 0]);
+        bytes.resize(128, 0);
+        pairs.data = 128;
+        bytes.extend([0b1010_0000]);
+        bytes.resize(192, 0);
+        bytes.extend([0b0100_0000]);
+        bytes.resize(256, 0);
+
+        let values: Vec<u16> = (0..7).map(|idx| pairs.decompress(&Reader(&bytes), idx).unwrap()).collect();
+        assert_eq!(values, [7, 3, 7, 7, 7, 3, 7]);
+    }
+
+    #[test]
+    fn probe_single_value_tables() {
+        //a made up KRvK table that is a win for white in turn and a loss for black in turn, so the distances follow from the table and not
+        //from chess. It checks the lookup, the color flip and the captures searched before the table, probe_real_tables checks real tables
+        let dir = std::env::temp_dir().join(format!("chess-tablebase-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("KRvK.rtbw"), single_value_table(Kind::Wdl, &[6, 4, 14], &[4, 0])).unwrap();
+        fs::write(dir.join("KRvK.rtbz"), single_value_table(Kind::Dtz, &[6, 4, 14], &[5])).unwrap();
+        fs::write(dir.join("KQvK.rtbw"), b"not a table").unwrap();
+        let tablebase = Tablebase::open(&dir).unwrap();
+        assert_eq!(tablebase.max_pieces(), 3);
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(tablebase.probe_wdl(&game), Ok(Wdl::Win));
+        assert_eq!(tablebase.probe_dtz(&game), Ok(11));
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        assert_eq!(tablebase.probe_wdl(&game), Ok(Wdl::Loss));
+        assert_eq!(tablebase.probe_dtz(&game), Ok(-12));
+
+        let game = Game::from_fen("4k3/4R3/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(tablebase.probe_wdl(&game), Ok(Wdl::Draw));
+
+        //the same material with the colors swapped uses the table flipped
+        let game = Game::from_fen("r3k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(tablebase.probe_wdl(&game), Ok(Wdl::Loss));
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let best = tablebase.best_move(&game).unwrap().unwrap();
+        assert_eq!((best.wdl, best.dtz), (Wdl::Win, 13));
+        let moves = tablebase.moves(&game).unwrap();
+        let hanging = moves.iter().find(|m| m.mv == game.parse_uci("d2d7").unwrap()).unwrap();
+        assert_eq!((hanging.wdl, hanging.dtz), (Wdl::Draw, 0));
+        assert_eq!(moves.last().unwrap().wdl, Wdl::Draw);
+
+        assert_eq!(tablebase.probe_wdl(&Game::from_fen("4k3/8/8/8/8/8/8/RN2K3 w - - 0 1").unwrap()), Err(TablebaseError::MissingTable(String::from("KRNvK"))));
+        assert_eq!(tablebase.probe_wdl(&Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap()), Err(TablebaseError::Castling));
+        assert_eq!(tablebase.probe_wdl(&Game::from_fen("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1").unwrap()), Err(TablebaseError::InvalidTable(String::from("KQvK"))));
+        assert!(matches!(Tablebase::open("/nonexistent/syzygy").err(), Some(TablebaseError::Io(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn probe_real_tables() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/test-data/syzygy");
+        let files = ["KQvK", "KRvK", "KPvK"].iter().flat_map(|t| [format!("{}.rtbw", t), format!("{}.rtbz", t)]);
+        let missing: Vec<String> = files.filter(|f| !Path::new(dir).join(f).is_file()).collect();
+        if !missing.is_empty() {
+            eprintln!("skipping probe_real_tables, test-data/syzygy lacks {}", missing.join(", "));
+            return;
+        }
+        let tablebase = Tablebase::open(dir).unwrap();
+        let fen = |fen: &str| Game::from_fen(fen).unwrap();
+
+        //mate in one with the queen, the rook and with colors swapped
+        for position in ["k7/8/1K6/8/8/8/7Q/8 w - - 0 1", "k7/8/1K6/8/8/8/8/7R w - - 0 1", "8/7q/8/8/8/1k6/8/K7 b - - 0 1"] {
+            let game = fen(position);
+            assert_eq!(tablebase.probe_wdl(&game), Ok(Wdl::Win), "{}", position);
+            assert_eq!(tablebase.probe_dtz(&game), Ok(1), "{}", position);
+            let best = tablebase.best_move(&game).unwrap().unwrap();
+            assert_eq!((best.wdl, best.dtz), (Wdl::Win, 1), "{}", position);
+            let mut after = game.clone();
+            after.try_move(best.mv).unwrap();
+            assert!(matches!(after.game_status, GameStatus::Checkmate(_)), "{}", position);
+        }
+
+        //black in turn with the queen or rook safe loses, a stalemate and a hanging queen are draws
+        let game = fen("k7/8/1K6/8/8/8/8/7Q b - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&game), Ok(Wdl::Loss));
+        assert!(tablebase.probe_dtz(&game).unwrap() < 0);
+        assert_eq!(tablebase.probe_wdl(&fen("k7/8/8/8/8/8/8/R3K3 b - - 0 1")), Ok(Wdl::Loss));
+        assert_eq!(tablebase.probe_wdl(&fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")), Ok(Wdl::Draw));
+        assert_eq!(tablebase.probe_dtz(&fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")), Ok(0));
+        assert_eq!(tablebase.probe_wdl(&fen("8/8/8/8/8/8/1k6/Q6K b - - 0 1")), Ok(Wdl::Draw));
+
+        //a rook pawn with the defending king in the corner is a draw, an unstoppable pawn wins
+        assert_eq!(tablebase.probe_wdl(&fen("k7/8/K7/P7/8/8/8/8 w - - 0 1")), Ok(Wdl::Draw));
+        assert_eq!(tablebase.probe_wdl(&fen("k7/8/K7/P7/8/8/8/8 b - - 0 1")), Ok(Wdl::Draw));
+        assert_eq!(tablebase.probe_wdl(&fen("8/8/8/8/8/8/3kP3/7K b - - 0 1")), Ok(Wdl::Draw));
+        let game = fen("k7/4P3/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&game), Ok(Wdl::Win));
+        assert_eq!(tablebase.probe_dtz(&game), Ok(1));
+        assert!(matches!(tablebase.best_move(&game).unwrap().unwrap().mv, Move::Promotion { .. }));
+        let game = fen("k7/4P3/8/8/8/8/8/4K3 b - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&game), Ok(Wdl::Loss));
+        assert!(tablebase.probe_dtz(&game).unwrap() < 0);
+        let game = fen("8/8/8/8/8/8/4P3/4K2k w - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&game), Ok(Wdl::Win));
+        assert_eq!(tablebase.probe_dtz(&game), Ok(1));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{Color, DrawReason, Game, GameResult, GameStatus, MoveError, Termination};
+
+impl Game {
+    ///the result of the game if it has ended, by a move, on time, by resignation, by agreement or by adjudication
+    pub fn result(&self) -> Option<GameResult> {
+        let (winner, termination) = match self.game_status {
+            GameStatus::Ongoing | GameStatus::Promoting => return None,
+            GameStatus::Checkmate(winner) => (Some(winner), Termination::Checkmate),
+            GameStatus::Timeout(winner) => (Some(winner), Termination::Timeout),
+            GameStatus::Resigned(winner) => (Some(winner), Termination::Resignation),
+            GameStatus::Adjudicated(winner) => (winner, Termination::Adjudication),
+            GameStatus::Draw(reason) => (None, match reason {
+                DrawReason::Stalemate => Termination::Stalemate,
+                DrawReason::InsufficientMaterial => Termination::InsufficientMaterial,
+                DrawReason::ThreefoldRepetition => Termination::ThreefoldRepetition,
+                DrawReason::FiftyMoveRule => Termination::FiftyMoveRule,
+                DrawReason::TimeoutVsInsufficientMaterial => Termination::TimeoutVsInsufficientMaterial,
+                DrawReason::Agreement => Termination::Agreement,
+            }),
+        };
+        Some(GameResult { winner, termination })
+    }
+
+    ///color gives up and the opponent wins. Either player can resign at any time while the game is not over.
+    pub fn resign(&mut self, color: Color) -> Result<(), MoveError> {
+        self.end_game(GameStatus::Resigned(color.opposite()))
+    }
+
+    /**
+    color offers a draw which the opponent can answer with accept_draw or decline_draw. The offer is also declined when the opponent makes a move.
+    An offer made by the player in turn stays open after the player's own move so it can be made before or after moving.
+    */
+    pub fn offer_draw(&mut self, color: Color) -> Result<(), MoveError> {
+        if self.result().is_some() {
+            return Err(MoveError::GameOver);
+        }
+        self.draw_offer = Some(color);
+        Ok(())
+    }
+
+    ///color accepts the draw offer of the opponent and the game ends in a draw by agreement
+    pub fn accept_draw(&mut self, color: Color) -> Result<(), MoveError> {
+        if self.result().is_some() {
+            return Err(MoveError::GameOver);
+        }
+        if self.draw_offer != Some(color.opposite()) {
+            return Err(MoveError::NoDrawOffer);
+        }
+        self.end_game(GameStatus::Draw(DrawReason::Agreement))
+    }
+
+    ///color declines the draw offer of the opponent and the game goes on
+    pub fn decline_draw(&mut self, color: Color) -> Result<(), MoveError> {
+        if self.result().is_some() {
+            return Err(MoveError::GameOver);
+        }
+        if self.draw_offer != Some(color.opposite()) {
+            return Err(MoveError::NoDrawOffer);
+        }
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    ///an arbiter ends the game with winner as the winner or None for a draw
+    pub fn adjudicate(&mut self, winner: Option<Color>) -> Result<(), MoveError> {
+        self.end_game(GameStatus::Adjudicated(winner))
+    }
+
+    ///the game ended by resignation, agreement, adjudication or on time rather than by the last move
+    pub(crate) fn ended_without_move(&self) -> bool {
+        self.result().is_some_and(|result| matches!(
+            result.termination,
+            Termination::Resignation | Termination::Agreement | Termination::Adjudication | Termination::Timeout | Termination::TimeoutVsInsufficientMaterial
+        ))
+    }
+
+    ///ends the game with status, stops the clock and drops the open draw offer
+    fn end_game(&mut self, status: GameStatus) -> Result<(), MoveError> {
+        if self.check_time() {
+            return Err(MoveError::Timeout);
+        }
+        if self.result().is_some() {
+            return Err(MoveError::GameOver);
+        }
+
+        self.game_status = status;
+        self.draw_offer = None;
+        if let Some(clock) = self.clock.as_mut() {
+            clock.stop();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Move;
+    use crate::test_util::{play, sq};
+    use crate::util::{File, Rank};
+
+    #[test]
+    fn resignation_ends_the_game() {
+        let mut game = Game::new();
+        assert_eq!(game.result(), None);
+        play(&mut game, "e4").unwrap();
+        game.resign(Color::Black).unwrap();
+
+        assert_eq!(game.game_status, GameStatus::Resigned(Color::White));
+        assert_eq!(game.result(), Some(GameResult { winner: Some(Color::White), termination: Termination::Resignation }));
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::E, Rank::R7), to: sq(File::E, Rank::R5) }), Err(MoveError::GameOver));
+        assert_eq!(crate::pgn::PgnResult::from_game(&game), crate::pgn::PgnResult::WhiteWins);
+        assert_eq!(game.resign(Color::White), Err(MoveError::GameOver));
+        assert_eq!(game.offer_draw(Color::White), Err(MoveError::GameOver));
+
+        //taking back the last move does not take back the resignation
+        assert_eq!(game.undo(), None);
+        assert_eq!(game.moves.len(), 1);
+        assert_eq!(game.result().map(|r| r.termination), Some(Termination::Resignation));
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::E, Rank::R7), to: sq(File::E, Rank::R5) }), Err(MoveError::GameOver));
+    }
+
+    #[test]
+    fn draw_offers() {
+        let mut game = Game::new();
+        assert_eq!(game.accept_draw(Color::Black), Err(MoveError::NoDrawOffer));
+
+        //white offers after its move and black declines by moving
+        play(&mut game, "e4").unwrap();
+        game.offer_draw(Color::White).unwrap();
+        assert_eq!(game.accept_draw(Color::White), Err(MoveError::NoDrawOffer));
+        play(&mut game, "e5").unwrap();
+        assert_eq!(game.draw_offer, None);
+        game.undo().unwrap();
+        assert_eq!(game.draw_offer, Some(Color::White));
+        play(&mut game, "e5").unwrap();
+
+        //white offers before its move and the offer stays open for black
+        game.offer_draw(Color::White).unwrap();
+        play(&mut game, "Nf3").unwrap();
+        assert_eq!(game.draw_offer, Some(Color::White));
+        game.decline_draw(Color::Black).unwrap();
+        assert_eq!(game.draw_offer, None);
+
+        game.offer_draw(Color::Black).unwrap();
+        game.accept_draw(Color::White).unwrap();
+        assert_eq!(game.game_status, GameStatus::Draw(DrawReason::Agreement));
+        assert_eq!(game.result(), Some(GameResult { winner: None, termination: Termination::Agreement }));
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::B, Rank::R8), to: sq(File::C, Rank::R6) }), Err(MoveError::GameOver));
+        assert_eq!(game.decline_draw(Color::White), Err(MoveError::GameOver));
+    }
+
+    #[test]
+    fn adjudication_and_finished_games() {
+        let mut game = Game::new();
+        game.adjudicate(None).unwrap();
+        assert_eq!(game.result(), Some(GameResult { winner: None, termination: Termination::Adjudication }));
+        assert_eq!(game.adjudicate(Some(Color::White)), Err(MoveError::GameOver));
+
+        let mut game = Game::from_fen("7k/8/6K1/8/8/8/5Q2/8 w - - 0 1").unwrap();
+        play(&mut game, "Qf7").unwrap();
+        assert_eq!(game.result(), Some(GameResult { winner: None, termination: Termination::Stalemate }));
+
+        //moves are refused after a draw by repetition even though the pieces can still move
+        let mut game = Game::new();
+        for san in ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"] {
+            play(&mut game, san).unwrap();
+        }
+        assert_eq!(game.result().map(|r| r.termination), Some(Termination::ThreefoldRepetition));
+        assert_eq!(game.try_move(Move::Normal { from: sq(File::G, Rank::R1), to: sq(File::F, Rank::R3) }), Err(MoveError::GameOver));
+    }
+}
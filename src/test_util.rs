@@ -0,0 +1,17 @@
+//helpers shared by the tests of the modules
+
+use crate::{Game, MoveError};
+use crate::util::{File, Rank, Square};
+
+///plays the moves given in SAN and separated by spaces. Panics if a move can't be parsed and returns the error of the first move that is refused.
+pub(crate) fn play(game: &mut Game, moves: &str) -> Result<(), MoveError> {
+    for san in moves.split_whitespace() {
+        let mv = game.parse_san(san).unwrap_or_else(|error| panic!("{}: {}", san, error));
+        game.try_move(mv)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn sq(file: File, rank: Rank) -> Square {
+    Square { file, rank }
+}
@@ -0,0 +1,397 @@
+use std::error;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{CastlingSide, Color, Game, Move};
+use crate::search::{MATE_BOUND, SearchLimits, SearchResult, Searcher};
+use crate::util::Square;
+
+impl Game {
+    /**
+    Parses a move in the long algebraic notation used by UCI, like "e2e4" or "e7e8q", against the current position.
+    Castling is written as the king move, "e1g1". Promotions need the promotion piece.
+    */
+    pub fn parse_uci(&self, uci: &str) -> Result<Move, UciError> {
+        let legal = self.legal_moves();
+        let mv = match uci.parse::<Move>().map_err(|_| UciError::InvalidMove)? {
+            Move::Normal { from, to } => match self.castling_side(from, to) {
+                Some(side) if legal.contains(&Move::Castle { side, color: self.turn }) => Move::Castle { side, color: self.turn },
+                _ => Move::Normal { from, to },
+            },
+            mv => mv,
+        };
+
+        match legal.contains(&mv) {
+            true => Ok(mv),
+            false => Err(UciError::IllegalMove),
+        }
+    }
+
+    ///formats a move of the player in turn in the long algebraic notation used by UCI
+    pub fn to_uci(&self, mv: Move) -> String {
+        match mv {
+            //Chess960 castles are written as the king taking its own rook, standard castles as the king moving two squares
+            Move::Castle { side, .. } => match self.castling_squares(side) {
+                Some((king, rook, _, _)) if self.chess960 => format!("{}{}", king, rook),
+                Some((king, _, king_to, _)) => format!("{}{}", king, king_to),
+                None => mv.to_string(),
+            },
+            _ => mv.to_string(),
+        }
+    }
+
+    ///the castling side if from to is the king move of a castle, either the king taking its own rook or in standard chess the king moving to its square
+    pub(crate) fn castling_side(&self, from: Square, to: Square) -> Option<CastlingSide> {
+        [CastlingSide::KingSide, CastlingSide::QueenSide].into_iter().find(|side| match self.castling_squares(*side) {
+            Some((king, rook, king_to, _)) => from == king && (to == rook || (!self.chess960 && to == king_to)),
+            None => false,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UciError {
+    InvalidMove,
+    IllegalMove,
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UciError::InvalidMove => write!(f, "Invalid move: the move is not written like e2e4 or e7e8q"),
+            UciError::IllegalMove => write!(f, "Illegal move: the move is not legal in the current position"),
+        }
+    }
+}
+
+impl error::Error for UciError { }
+
+type Output = Arc<Mutex<dyn Write + Send>>;
+
+/**
+A UCI engine built on Game and the Searcher. Commands are read line by line with handle and the answers are written to the output.
+The search runs in its own thread so stop and isready are answered while it is running.
+*/
+pub struct UciEngine {
+    game: Game,
+    output: Output,
+    searcher: Option<Searcher>,
+    stop: Arc<AtomicBool>,
+    ///set by stop and quit. A search started with go infinite waits for it before it sends bestmove, even if it finished on its own.
+    stop_requested: Arc<AtomicBool>,
+    search_thread: Option<JoinHandle<Searcher>>,
+    ///whether the running search was started with go infinite
+    infinite: bool,
+    ///set with the UCI_Chess960 option
+    chess960: bool,
+}
+
+impl UciEngine {
+    pub fn new(output: impl Write + Send + 'static) -> UciEngine {
+        let searcher = Searcher::new();
+        UciEngine {
+            game: Game::new(),
+            output: Arc::new(Mutex::new(output)),
+            stop: searcher.stop_flag(),
+            stop_requested: Arc::new(AtomicBool::new(false)),
+            searcher: Some(searcher),
+            search_thread: None,
+            infinite: false,
+            chess960: false,
+        }
+    }
+
+    ///reads commands from input until quit or the end of the input
+    pub fn run(&mut self, input: impl BufRead) {
+        for line in input.lines() {
+            let Ok(line) = line else { break };
+            if !self.handle(&line) {
+                return;
+            }
+        }
+        self.stop_search();
+    }
+
+    ///handles one command and returns false when the engine should quit
+    pub fn handle(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => {
+                self.send(&format!("id name chess {}", env!("CARGO_PKG_VERSION")));
+                self.send("id author erikfran");
+                self.send("option name UCI_Chess960 type check default false");
+                self.send("uciok");
+            },
+            Some("isready") => self.send("readyok"),
+            Some("setoption") => {
+                let tokens: Vec<&str> = tokens.collect();
+                if let ["name", "UCI_Chess960", "value", value] = tokens[..] {
+                    self.chess960 = value == "true";
+                }
+            },
+            Some("ucinewgame") => {
+                self.finish_search();
+                self.game = Game::new();
+                if let Some(searcher) = self.searcher.as_mut() {
+                    searcher.new_game();
+                }
+            },
+            Some("position") => {
+                self.finish_search();
+                let tokens: Vec<&str> = tokens.collect();
+                if let Err(error) = self.position(&tokens) {
+                    self.send(&format!("info string {}", error));
+                }
+            },
+            Some("go") => {
+                self.finish_search();
+                let tokens: Vec<&str> = tokens.collect();
+                self.go(&tokens);
+            },
+            Some("stop") => self.stop_search(),
+            Some("quit") => {
+                self.stop_search();
+                return false;
+            },
+            _ => {},
+        }
+        true
+    }
+
+    fn send(&self, line: &str) {
+        send(&self.output, line);
+    }
+
+    fn position(&mut self, tokens: &[&str]) -> Result<(), Box<dyn error::Error>> {
+        let moves_at = tokens.iter().position(|t| *t == "moves").unwrap_or(tokens.len());
+        let mut game = match tokens.first() {
+            Some(&"startpos") => Game::new(),
+            Some(&"fen") => Game::from_fen(&tokens[1..moves_at].join(" "))?,
+            _ => return Err("position needs startpos or fen".into()),
+        };
+        game.chess960 |= self.chess960;
+
+        for uci in tokens.iter().skip(moves_at + 1) {
+            let mv = game.parse_uci(uci)?;
+            game.try_move(mv)?;
+        }
+
+        self.game = game;
+        Ok(())
+    }
+
+    fn go(&mut self, tokens: &[&str]) {
+        let value = |name: &str| tokens.iter()
+            .position(|t| *t == name)
+            .and_then(|i| tokens.get(i + 1))
+            .and_then(|v| v.parse::<u64>().ok());
+
+        let infinite = tokens.contains(&"infinite");
+        self.infinite = infinite;
+        let mut limits = SearchLimits { depth: 100, movetime: None, nodes: value("nodes") };
+        if let Some(depth) = value("depth") {
+            limits.depth = depth as u32;
+        }
+        if let Some(movetime) = value("movetime") {
+            limits.movetime = Some(Duration::from_millis(movetime));
+        }
+        else if !infinite {
+            let (time, increment) = match self.game.turn {
+                Color::White => (value("wtime"), value("winc")),
+                Color::Black => (value("btime"), value("binc")),
+            };
+            if let Some(time) = time {
+                limits.movetime = Some(time_for_move(time, increment.unwrap_or(0), value("movestogo")));
+            }
+        }
+
+        let Some(mut searcher) = self.searcher.take() else { return };
+        let game = self.game.clone();
+        let output = Arc::clone(&self.output);
+        let stop = searcher.stop_flag();
+        let stop_requested = Arc::clone(&self.stop_requested);
+        stop_requested.store(false, Ordering::Relaxed);
+
+        self.search_thread = Some(std::thread::spawn(move || {
+            let result = searcher.search_with_info(&game, limits, |info| {
+                //the search clears its stop flag when it starts, so a stop that came before that is passed on here
+                if stop_requested.load(Ordering::Relaxed) {
+                    stop.store(true, Ordering::Relaxed);
+                }
+                send(&output, &info_line(&game, info));
+            });
+
+            //UCI does not allow bestmove before stop in an infinite search
+            while infinite && !stop_requested.load(Ordering::Relaxed) {
+                std::thread::park();
+            }
+            let best = result.best_move.map_or(String::from("0000"), |mv| game.to_uci(mv));
+            send(&output, &format!("bestmove {}", best));
+            searcher
+        }));
+    }
+
+    fn wait_for_search(&mut self) {
+        if let Some(thread) = self.search_thread.take() {
+            let searcher = thread.join().unwrap_or_default();
+            self.stop = searcher.stop_flag();
+            self.searcher = Some(searcher);
+        }
+    }
+
+    ///waits for a search with limits to end and stops an infinite search, which would otherwise wait for stop forever
+    fn finish_search(&mut self) {
+        match self.infinite {
+            true => self.stop_search(),
+            false => self.wait_for_search(),
+        }
+    }
+
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.stop_requested.store(true, Ordering::Relaxed);
+        if let Some(thread) = &self.search_thread {
+            thread.thread().unpark();
+        }
+        self.wait_for_search();
+    }
+}
+
+fn send(output: &Output, line: &str) {
+    if let Ok(mut output) = output.lock() {
+        let _ = writeln!(output, "{}", line);
+        let _ = output.flush();
+    }
+}
+
+///uses a part of the remaining time and most of the increment, keeping a margin for the communication
+fn time_for_move(time: u64, increment: u64, moves_to_go: Option<u64>) -> Duration {
+    let moves_to_go = moves_to_go.unwrap_or(30).max(1);
+    let margin = 50.min(time / 2);
+    let time_for_move = (time / moves_to_go + increment * 3 / 4).min(time - margin);
+    Duration::from_millis(time_for_move.max(1))
+}
+
+fn info_line(game: &Game, info: &SearchResult) -> String {
+    let score = match info.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score.clamp(-MATE_BOUND, MATE_BOUND)),
+    };
+    let millis = info.time.as_millis().max(1) as u64;
+
+    let mut position = game.clone();
+    let mut pv = vec![];
+    for mv in &info.pv {
+        pv.push(position.to_uci(*mv));
+        if position.try_move(*mv).is_err() {
+            break;
+        }
+    }
+
+    format!("info depth {} score {} nodes {} nps {} time {} pv {}", info.depth, score, info.nodes, info.nodes * 1000 / millis, millis, pv.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::sq;
+    use crate::PieceTypes;
+    use crate::util::{File, Rank};
+
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run(commands: &str) -> Vec<String> {
+        let output = SharedOutput::default();
+        UciEngine::new(output.clone()).run(commands.as_bytes());
+        let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn uci_moves() {
+        let game = Game::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(game.parse_uci("e1g1"), Ok(Move::Castle { side: CastlingSide::KingSide, color: Color::White }));
+        assert_eq!(game.parse_uci("e1c1"), Ok(Move::Castle { side: CastlingSide::QueenSide, color: Color::White }));
+        assert_eq!(game.parse_uci("e1f1"), Ok(Move::Normal { from: sq(File::E, Rank::R1), to: sq(File::F, Rank::R1) }));
+        assert_eq!(game.parse_uci("b7a8n"), Ok(Move::Promotion { from: sq(File::B, Rank::R7), to: sq(File::A, Rank::R8), piece: PieceTypes::Knight }));
+        assert_eq!(game.parse_uci("b7b8"), Err(UciError::IllegalMove));
+        assert_eq!(game.parse_uci("e1e3"), Err(UciError::IllegalMove));
+        assert_eq!(game.parse_uci("e1"), Err(UciError::InvalidMove));
+        assert_eq!(game.parse_uci("b7b8k"), Err(UciError::InvalidMove));
+
+        for mv in game.legal_moves() {
+            assert_eq!(game.parse_uci(&game.to_uci(mv)), Ok(mv));
+        }
+    }
+
+    #[test]
+    fn handshake() {
+        let lines = run("uci\nisready\nquit\n");
+        assert_eq!(lines.last().map(String::as_str), Some("readyok"));
+        assert!(lines.contains(&String::from("uciok")));
+    }
+
+    #[test]
+    fn go_depth_from_position() {
+        //the next position command waits for the search to finish
+        let lines = run("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 3\nposition startpos moves e2e4 e7e5 g1f3\ngo depth 2\nposition startpos\nisready\n");
+        let best: Vec<&String> = lines.iter().filter(|l| l.starts_with("bestmove")).collect();
+        assert_eq!(best[0], "bestmove a1a8");
+        assert!(lines.iter().any(|l| l.starts_with("info depth 1 score mate 1")));
+        assert_eq!(best.len(), 2);
+        assert!(lines.iter().any(|l| l.starts_with("info depth 2")));
+    }
+
+    #[test]
+    fn stop_infinite_search() {
+        let output = SharedOutput::default();
+        let mut engine = UciEngine::new(output.clone());
+        engine.handle("position startpos");
+        engine.handle("go infinite");
+        std::thread::sleep(Duration::from_millis(50));
+        engine.handle("stop");
+        let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(text.lines().last().unwrap().starts_with("bestmove "));
+
+        //a mate in one is found at once but bestmove still waits for stop
+        let output = SharedOutput::default();
+        let mut engine = UciEngine::new(output.clone());
+        engine.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        engine.handle("go infinite");
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!String::from_utf8(output.0.lock().unwrap().clone()).unwrap().contains("bestmove"));
+        engine.handle("stop");
+        let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(text.lines().last(), Some("bestmove a1a8"));
+
+        //a new position ends an infinite search that was not stopped
+        let output = SharedOutput::default();
+        let mut engine = UciEngine::new(output.clone());
+        engine.handle("go infinite");
+        engine.handle("position startpos");
+        engine.handle("isready");
+        let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[lines.len() - 2].starts_with("bestmove "));
+        assert_eq!(lines.last(), Some(&"readyok"));
+
+        assert_eq!(time_for_move(60_000, 1_000, None), Duration::from_millis(2_750));
+        assert_eq!(time_for_move(100, 0, Some(1)), Duration::from_millis(50));
+    }
+}
@@ -1,23 +1,132 @@
+use std::error;
+use std::fmt;
 use std::ops::{Index, IndexMut};
-use crate::{Move, Piece};
-
+use std::str::FromStr;
+use crate::{Color, Move, Piece, PieceTypes};
+use crate::bitboard::{self, Bitboard};
+use crate::zobrist;
+
+/** The position of the pieces. Reading and writing works by indexing with a Square or a Rank and then a File like before, and rows and rows_mut
+give all the squares. set is the fast way to change a square since it updates the bitboards used by the move generator right away. A rank written
+through indexing is remembered and the bitboards are patched with its squares until the next set or indexed write folds it into them. */
 #[derive(Clone, Copy)]
 pub struct Board {
-    pub rows: Rows<Rows<Option<Piece>>>,
+    rows: Rows<Rows<Option<Piece>>>,
+    pieces: [Bitboard; 6],
+    colors: [Bitboard; 2],
+    key: u64,
+    ///one bit for every rank written through indexing. pieces, colors and key still hold the pieces that stood on these ranks before.
+    written: u8,
+}
+
+///the piece types in the order of bitboard::piece_index
+const PIECE_TYPES: [PieceTypes; 6] = [PieceTypes::Pawn(false), PieceTypes::Bishop, PieceTypes::Knight, PieceTypes::Rook, PieceTypes::Queen, PieceTypes::King];
+
+impl Board {
+    ///puts the piece on the square or clears it with None
+    pub fn set(&mut self, square: Square, piece: Option<Piece>) {
+        self.sync();
+
+        let bit = bitboard::bit(square);
+        if let Some(old) = self.rows.squares[square.rank as usize][square.file] {
+            self.pieces[bitboard::piece_index(old.piece)] &= !bit;
+            self.colors[bitboard::color_index(old.color)] &= !bit;
+            self.key ^= zobrist::piece_key(old, square);
+        }
+        if let Some(new) = piece {
+            self.pieces[bitboard::piece_index(new.piece)] |= bit;
+            self.colors[bitboard::color_index(new.color)] |= bit;
+            self.key ^= zobrist::piece_key(new, square);
+        }
+        self.rows.squares[square.rank as usize][square.file] = piece;
+    }
+
+    ///the rows of squares, indexed by rank and then file
+    pub fn rows(&self) -> &Rows<Rows<Option<Piece>>> {
+        &self.rows
+    }
+
+    ///the rows of squares for writing. Like indexing it marks every rank as written.
+    pub fn rows_mut(&mut self) -> &mut Rows<Rows<Option<Piece>>> {
+        self.sync();
+        self.written = u8::MAX;
+        &mut self.rows
+    }
+
+    ///the squares of the written ranks
+    fn written_squares(&self) -> Bitboard {
+        (0..8).filter(|rank| self.written & 1 << rank != 0).fold(0, |squares, rank| squares | 0xff << (rank * 8))
+    }
+
+    ///the piece the bitboards have on square, which only differs from the squares on a written rank
+    fn bitboard_piece(&self, square: Square) -> Option<Piece> {
+        let bit = bitboard::bit(square);
+        let color = [Color::White, Color::Black].into_iter().find(|color| self.colors[bitboard::color_index(*color)] & bit != 0)?;
+        let piece = PIECE_TYPES.into_iter().find(|piece| self.pieces[bitboard::piece_index(*piece)] & bit != 0)?;
+        Some(Piece { piece, color })
+    }
+
+    ///the written squares that hold a piece matching f
+    fn written_bits(&self, f: impl Fn(Piece) -> bool) -> Bitboard {
+        bitboard::squares(self.written_squares())
+            .filter(|square| self[*square].is_some_and(&f))
+            .fold(0, |bits, square| bits | bitboard::bit(square))
+    }
+
+    ///folds the squares of the written ranks into the bitboards and key
+    fn sync(&mut self) {
+        let written = self.written_squares();
+        self.written = 0;
+        for square in bitboard::squares(written) {
+            let piece = self.rows.squares[square.rank as usize][square.file];
+            self.rows.squares[square.rank as usize][square.file] = self.bitboard_piece(square);
+            self.set(square, piece);
+        }
+    }
+
+    ///the Zobrist key of the pieces on the board
+    pub(crate) fn key(&self) -> u64 {
+        bitboard::squares(self.written_squares()).fold(self.key, |key, square| {
+            let key = self.bitboard_piece(square).map_or(key, |old| key ^ zobrist::piece_key(old, square));
+            self[square].map_or(key, |new| key ^ zobrist::piece_key(new, square))
+        })
+    }
+
+    pub(crate) fn occupied(&self) -> Bitboard {
+        self.color(Color::White) | self.color(Color::Black)
+    }
+
+    pub(crate) fn color(&self, color: Color) -> Bitboard {
+        let bits = self.colors[bitboard::color_index(color)];
+        match self.written {
+            0 => bits,
+            _ => bits & !self.written_squares() | self.written_bits(|piece| piece.color == color),
+        }
+    }
+
+    pub(crate) fn pieces(&self, piece: PieceTypes, color: Color) -> Bitboard {
+        let index = bitboard::piece_index(piece);
+        let bits = self.pieces[index] & self.colors[bitboard::color_index(color)];
+        match self.written {
+            0 => bits,
+            _ => bits & !self.written_squares() | self.written_bits(|p| bitboard::piece_index(p.piece) == index && p.color == color),
+        }
+    }
 }
 
 impl From<[[Option<Piece>; 8]; 8]> for Board {
     fn from (value: [[Option<Piece>; 8]; 8]) -> Self {
-        Board { rows: Rows { squares: [
-            Rows { squares: value[0] },
-            Rows { squares: value[1] },
-            Rows { squares: value[2] },
-            Rows { squares: value[3] },
-            Rows { squares: value[4] },
-            Rows { squares: value[5] },
-            Rows { squares: value[6] },
-            Rows { squares: value[7] },
-        ] } }
+        let mut board = Board {
+            rows: Rows { squares: [Rows { squares: [None; 8] }; 8] },
+            pieces: [0; 6],
+            colors: [0; 2],
+            key: 0,
+            written: 0,
+        };
+        for square in get_square_array() {
+            board.set(square, value[square.rank as usize][square.file as usize]);
+        }
+        board
     }
 }
 
@@ -45,11 +154,14 @@ impl Index<Rank> for Board {
 
 impl IndexMut<Rank> for Board {
     fn index_mut(&mut self, rank: Rank) -> &mut Self::Output {
+        self.sync();
+        self.written = 1 << rank as u8;
         &mut self.rows.squares[rank as usize]
     }
 }
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoardMove {
     pub rows: Rows<Rows<Option<Move>>>,
 }
@@ -98,6 +210,7 @@ impl IndexMut<Rank> for BoardMove {
 }
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rows<T> {
     pub squares: [T; 8],
 }
@@ -143,6 +256,28 @@ impl TryFrom<(i32, i32)> for Square {
     }
 }
 
+///Written in algebraic notation, e.g. "e4".
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file, self.rank)
+    }
+}
+
+impl FromStr for Square {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (file, rank) = match s.char_indices().nth(1) {
+            Some((i, _)) => s.split_at(i),
+            None => return Err(ParseError::InvalidSquare),
+        };
+        Ok(Square {
+            file: file.parse().map_err(|_| ParseError::InvalidSquare)?,
+            rank: rank.parse().map_err(|_| ParseError::InvalidSquare)?,
+        })
+    }
+}
+
 pub const FILE_ARRAY: [File; 8] = [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
 pub const RANK_ARRAY: [Rank; 8]  = [Rank::R1, Rank::R2, Rank::R3, Rank::R4, Rank::R5, Rank::R6, Rank::R7, Rank::R8];
 pub const fn get_square_array() -> [Square; 64]{
@@ -184,6 +319,24 @@ impl File {
     }
 }
 
+///Written as the lowercase letter of the file, e.g. "e".
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", (b'a' + self.num() as u8) as char)
+    }
+}
+
+impl FromStr for File {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            [c] => File::try_from(*c as i32 - 'a' as i32).map_err(|_| ParseError::InvalidFile),
+            _ => Err(ParseError::InvalidFile),
+        }
+    }
+}
+
 impl From<File> for i32 {
     fn from(file: File) -> i32 {
         file as i32
@@ -231,6 +384,24 @@ impl Rank {
     }
 }
 
+///Written as the number of the rank, e.g. "4".
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.num() + 1)
+    }
+}
+
+impl FromStr for Rank {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            [c] => Rank::try_from(*c as i32 - '1' as i32).map_err(|_| ParseError::InvalidRank),
+            _ => Err(ParseError::InvalidRank),
+        }
+    }
+}
+
 impl From<Rank> for i32 {
     fn from(rank: Rank) -> i32 {
         rank as i32
@@ -254,3 +425,28 @@ impl TryFrom<i32> for Rank {
         }
     }
 }
+
+///The error of parsing a square, file, rank, piece or move from its text form with FromStr.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParseError {
+    InvalidFile,
+    InvalidRank,
+    InvalidSquare,
+    InvalidPiece,
+    InvalidMove,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::InvalidFile => write!(f, "Invalid file: a file is a letter from a to h"),
+            ParseError::InvalidRank => write!(f, "Invalid rank: a rank is a number from 1 to 8"),
+            ParseError::InvalidSquare => write!(f, "Invalid square: a square is a file and a rank like e4"),
+            ParseError::InvalidPiece => write!(f, "Invalid piece: a piece is one of the FEN letters PNBRQK or pnbrqk"),
+            ParseError::InvalidMove => write!(f, "Invalid move: a move is written in UCI like e2e4 or e7e8q"),
+        }
+    }
+}
+
+impl error::Error for ParseError { }
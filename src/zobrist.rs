@@ -0,0 +1,153 @@
+use crate::{Color, Game, Piece, PieceTypes};
+use crate::bitboard;
+use crate::util::{Rank, Square};
+
+///random keys for every piece type and color on every square, indexed by color, piece type and square
+const PIECE_KEYS: [[[u64; 64]; 6]; 2] = piece_keys();
+const SIDE_KEY: u64 = random(12 * 64);
+///white king side, white queen side, black king side and black queen side
+const CASTLING_KEYS: [u64; 4] = [random(12 * 64 + 1), random(12 * 64 + 2), random(12 * 64 + 3), random(12 * 64 + 4)];
+const EN_PASSANT_KEYS: [u64; 8] = en_passant_keys();
+
+///the n:th output of the splitmix64 generator, used to fill the key tables at compile time
+const fn random(n: u64) -> u64 {
+    let mut z = 0x9e3779b97f4a7c15u64.wrapping_mul(n + 1);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+const fn piece_keys() -> [[[u64; 64]; 6]; 2] {
+    let mut keys = [[[0; 64]; 6]; 2];
+    let mut i = 0;
+    while i < 12 * 64 {
+        keys[i / (6 * 64)][(i / 64) % 6][i % 64] = random(i as u64);
+        i += 1;
+    }
+    keys
+}
+
+const fn en_passant_keys() -> [u64; 8] {
+    let mut keys = [0; 8];
+    let mut file = 0;
+    while file < 8 {
+        keys[file] = random(12 * 64 + 5 + file as u64);
+        file += 1;
+    }
+    keys
+}
+
+///the key of a piece standing on a square. The board xors these in and out as pieces are placed and removed.
+pub(crate) fn piece_key(piece: Piece, square: Square) -> u64 {
+    PIECE_KEYS[bitboard::color_index(piece.color)][bitboard::piece_index(piece.piece)][bitboard::index(square)]
+}
+
+impl Game {
+    /**
+    The 64 bit Zobrist key of the current position. It covers the pieces, the player in turn, the castling rights and the en passant file when an en passant capture is possible.
+    The piece part is updated incrementally by every move and undo so the key is cheap to get.
+    Equal positions have equal keys which makes it useful for repetition detection and transposition tables.
+    */
+    pub fn hash(&self) -> u64 {
+        let mut key = self.board.key();
+
+        if self.turn == Color::Black {
+            key ^= SIDE_KEY;
+        }
+
+        let rights = [self.castling.white.0, self.castling.white.1, self.castling.black.0, self.castling.black.1];
+        for (right, castling_key) in rights.iter().zip(CASTLING_KEYS) {
+            if right.is_some() {
+                key ^= castling_key;
+            }
+        }
+
+        if let Some(square) = self.en_passant.filter(|_| self.en_passant_capturable()) {
+            key ^= EN_PASSANT_KEYS[square.file as usize];
+        }
+
+        key
+    }
+
+    ///whether a pawn of the player in turn stands next to the pawn that just made a double step.
+    ///The en passant square only counts towards the key then, so a double step that can't be taken doesn't break repetitions.
+    pub(crate) fn en_passant_capturable(&self) -> bool {
+        let Some(square) = self.en_passant else {
+            return false;
+        };
+        let pawn_rank = match self.turn {
+            Color::White => Rank::R5,
+            Color::Black => Rank::R4,
+        };
+        [-1, 1].into_iter().any(|side| {
+            Square::try_from((square.file.num() + side, pawn_rank.num())).is_ok_and(|from| matches!(
+                self.board[from],
+                Some(piece) if piece.color == self.turn && matches!(piece.piece, PieceTypes::Pawn(_))
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DrawReason, Game, GameStatus};
+    use crate::test_util::play;
+
+    #[test]
+    fn transpositions_have_equal_keys() {
+        let mut a = Game::new();
+        let mut b = Game::new();
+        play(&mut a, "Nf3 Nf6 Nc3 d6").unwrap();
+        play(&mut b, "Nc3 d6 Nf3 Nf6").unwrap();
+        assert_eq!(a.hash(), b.hash());
+        assert_eq!(a.hash(), Game::from_fen(&a.to_fen()).unwrap().hash());
+        assert_ne!(a.hash(), Game::new().hash());
+    }
+
+    #[test]
+    fn side_castling_and_en_passant_change_the_key() {
+        let hash = |fen: &str| Game::from_fen(fen).unwrap().hash();
+        let base = hash("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq - 0 1");
+        assert_ne!(base, hash("r3k2r/8/8/3pP3/8/8/8/R3K2R b KQkq - 0 1"));
+        assert_ne!(base, hash("r3k2r/8/8/3pP3/8/8/8/R3K2R w Kkq - 0 1"));
+        assert_ne!(base, hash("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1"));
+        assert_eq!(base, hash("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq - 10 20"));
+    }
+
+    #[test]
+    fn double_step_without_capture_repeats() {
+        //no black pawn can take on e3, so the position after e4 is the same as after the knights went back
+        let mut game = Game::new();
+        play(&mut game, "e4").unwrap();
+        let after_double_step = game.hash();
+        play(&mut game, "Nf6 Nf3 Ng8 Ng1").unwrap();
+        assert_eq!(game.hash(), after_double_step);
+        play(&mut game, "Nf6 Nf3 Ng8").unwrap();
+        assert_eq!(game.game_status, GameStatus::Ongoing);
+        play(&mut game, "Ng1").unwrap();
+        assert_eq!(game.game_status, GameStatus::Draw(DrawReason::ThreefoldRepetition));
+
+        //with a pawn next to it the en passant square is part of the position
+        let capturable = Game::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+        let mut pushed = capturable.clone();
+        play(&mut pushed, "e4").unwrap();
+        assert_ne!(pushed.hash(), Game::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap().hash());
+        assert_eq!(pushed.hash(), Game::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap().hash());
+    }
+
+    #[test]
+    fn undo_restores_the_key() {
+        let mut game = Game::new();
+        let mut keys = vec![game.hash()];
+        for san in ["e4", "d5", "exd5", "c5", "dxc6", "Qa5", "cxb7", "Kd8", "bxa8=Q", "e5", "Qxb8", "Ba3", "Nxa3", "Ke7", "Nf3", "Nf6", "Be2", "Re8", "O-O"] {
+            play(&mut game, san).unwrap();
+            assert_eq!(game.hash(), Game::from_fen(&game.to_fen()).unwrap().hash());
+            keys.push(game.hash());
+        }
+
+        while game.undo().is_some() {
+            keys.pop();
+            assert_eq!(game.hash(), *keys.last().unwrap());
+        }
+    }
+}